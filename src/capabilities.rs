@@ -0,0 +1,150 @@
+#[cfg(feature = "v0_26")]
+use std::ffi::CString;
+
+#[cfg(unix)]
+use libc;
+
+use ffi;
+
+/// The parts of the notmuch API offered by the linked libnotmuch.
+///
+/// libnotmuch does not report its version at runtime, so it is told from
+/// the functions the library exports. A method of this crate additionally
+/// needs the matching `v0_*` feature to be compiled in; see
+/// `built_features` for what this build of the crate supports. Optional
+/// features that notmuch itself may be built without are queried from the
+/// library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The notmuch version, as (major, minor), whose API the library
+    /// provides. Releases that added no functions are reported as the
+    /// release before them.
+    pub notmuch_version: (u32, u32),
+    /// `Database::revision`.
+    pub revision: bool,
+    /// `Message::count_files` and `Thread::total_files`.
+    pub count_files: bool,
+    /// `Database::open_with_config`, `Database::create_with_config`,
+    /// `Database::config_values` and `Message::reindex_and_apply_new_tags`.
+    pub open_with_config: bool,
+    /// `Database::reopen`.
+    pub reopen: bool,
+    /// `Database::create_query_with_syntax`.
+    pub query_syntax: bool,
+    /// notmuch was built with support for `Database::compact`.
+    pub compact: bool,
+    /// notmuch was built with Xapian field processors, needed for queries
+    /// such as `query:` and regex searches.
+    pub field_processor: bool,
+    /// notmuch was built with support for retrying to acquire the write lock.
+    pub retry_lock: bool,
+    /// notmuch was built with support for s-expression queries.
+    pub sexp_queries: bool,
+}
+
+/// Check whether libnotmuch was built with the optional feature `name`,
+/// such as `"compact"`, `"field_processor"` or `"session_key"`.
+///
+/// Unknown names, and every name before the `v0_26` feature, report
+/// `false`.
+pub fn built_with(name: &str) -> bool {
+    #[cfg(feature = "v0_26")]
+    {
+        match CString::new(name) {
+            Ok(name) => unsafe { ffi::notmuch_built_with(name.as_ptr()) != 0 },
+            Err(_) => false,
+        }
+    }
+    #[cfg(not(feature = "v0_26"))]
+    {
+        let _ = name;
+        false
+    }
+}
+
+/// Report the parts of the notmuch API offered by the linked library.
+#[cfg(unix)]
+pub fn capabilities() -> Capabilities {
+    let revision = has_symbol("notmuch_database_get_revision\0");
+    let count_files = has_symbol("notmuch_message_count_files\0");
+    let open_with_config = has_symbol("notmuch_database_open_with_config\0");
+    let reopen = has_symbol("notmuch_database_reopen\0");
+    let query_syntax = has_symbol("notmuch_query_create_with_syntax\0");
+
+    let notmuch_version = if reopen {
+        (0, 35)
+    } else if has_symbol("notmuch_message_get_flag_st\0") {
+        (0, 34)
+    } else if open_with_config {
+        (0, 32)
+    } else if has_symbol("notmuch_database_index_file\0") {
+        (0, 26)
+    } else if count_files {
+        (0, 25)
+    } else if revision {
+        (0, 21)
+    } else {
+        (0, 0)
+    };
+
+    Capabilities {
+        notmuch_version,
+        revision,
+        count_files,
+        open_with_config,
+        reopen,
+        query_syntax,
+        compact: built_with("compact"),
+        field_processor: built_with("field_processor"),
+        retry_lock: built_with("retry_lock"),
+        sexp_queries: built_with("sexp_queries"),
+    }
+}
+
+/// Report the parts of the notmuch API offered by the linked library.
+///
+/// Without `dlsym` to probe the library, it is assumed to match the build.
+#[cfg(not(unix))]
+pub fn capabilities() -> Capabilities {
+    built_features()
+}
+
+/// Report the parts of the notmuch API this build of the crate was
+/// compiled to use, as selected by the `v0_*` features. Each of these
+/// requires at least that version of the linked library.
+pub fn built_features() -> Capabilities {
+    let notmuch_version = if cfg!(feature = "v0_35") {
+        (0, 35)
+    } else if cfg!(feature = "v0_34") {
+        (0, 34)
+    } else if cfg!(feature = "v0_32") {
+        (0, 32)
+    } else if cfg!(feature = "v0_26") {
+        (0, 26)
+    } else if cfg!(feature = "v0_21") {
+        (0, 21)
+    } else {
+        (0, 0)
+    };
+
+    Capabilities {
+        notmuch_version,
+        revision: cfg!(feature = "v0_21"),
+        count_files: cfg!(feature = "v0_26"),
+        open_with_config: cfg!(feature = "v0_32"),
+        reopen: cfg!(feature = "v0_35"),
+        query_syntax: cfg!(feature = "v0_35"),
+        compact: built_with("compact"),
+        field_processor: built_with("field_processor"),
+        retry_lock: built_with("retry_lock"),
+        sexp_queries: built_with("sexp_queries"),
+    }
+}
+
+// Whether the process can resolve the function `name`, given with its
+// trailing nul. libnotmuch is linked dynamically, so its exports are in
+// the global scope.
+#[cfg(unix)]
+fn has_symbol(name: &str) -> bool {
+    !unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr() as *const libc::c_char) }.is_null()
+}
@@ -1,7 +1,9 @@
 use std::ffi::{CStr, CString};
 use std::ops::Drop;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
 
 use supercow::Supercow;
 
@@ -11,12 +13,18 @@ use std::cmp::{PartialEq, PartialOrd, Ordering};
 use error::{Error, Result};
 use ffi;
 use ffi::Status;
-use utils::ToStr;
+#[cfg(feature = "v0_35")]
+use ffi::QuerySyntax;
+use utils::{ToStr, ToPath, path_to_cstring};
 use Directory;
 use Query;
+use QueryExt;
+use QueryBuilder;
 use Tags;
+use Threads;
 use TagsOwner;
 use Message;
+use Messages;
 use MessageOwner;
 use IndexOpts;
 use ConfigList;
@@ -49,12 +57,34 @@ impl PartialOrd for Revision {
 }
 
 
-#[derive(Debug)]
+/// A handle on a notmuch database.
+///
+/// Cloning a `Database` is cheap: clones share one underlying notmuch
+/// handle, including its mode and atomic nesting, and the database is
+/// closed when the last clone is dropped.
+///
+/// A `Database` can be sent to another thread and used there, but notmuch
+/// handles are not safe for concurrent use: clones must never be used from
+/// more than one thread at the same time.
+///
+/// Writes are a different matter. notmuch lets only one read-write handle
+/// be open for a database at any time, across all processes, and an atomic
+/// operation begun through one clone spans the changes made through all of
+/// them until it ends.
+#[derive(Clone, Debug)]
 pub struct Database {
-    pub(crate) ptr: *mut ffi::notmuch_database_t,
+    handle: Arc<DatabaseHandle>,
+}
+
+// The state shared by all clones of a `Database`.
+#[derive(Debug)]
+struct DatabaseHandle {
+    ptr: *mut ffi::notmuch_database_t,
+    read_only: AtomicBool,
+    atomic_depth: AtomicUsize,
 }
 
-impl Drop for Database {
+impl Drop for DatabaseHandle {
     fn drop(&mut self) {
         unsafe { ffi::notmuch_database_destroy(self.ptr) };
     }
@@ -64,50 +94,189 @@ impl TagsOwner for Database {}
 impl MessageOwner for Database {}
 
 impl Database {
+    pub(crate) fn from_ptr(ptr: *mut ffi::notmuch_database_t, mode: DatabaseMode) -> Database {
+        Database {
+            handle: Arc::new(DatabaseHandle {
+                ptr,
+                read_only: AtomicBool::new(mode == DatabaseMode::ReadOnly),
+                atomic_depth: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Start collecting the options for opening a database. See
+    /// `DatabaseOpener`.
+    pub fn opener() -> DatabaseOpener {
+        DatabaseOpener::new()
+    }
+
+    /// Create a new, empty database for the mail stored under `path`,
+    /// opened read-write. Fails if a database already exists there.
     pub fn create<P>(path: &P) -> Result<Self>
     where
         P: AsRef<Path>,
     {
-        let path_str = CString::new(path.as_ref().to_str().unwrap()).unwrap();
+        let path_str = path_to_cstring(path)?;
 
         let mut db = ptr::null_mut();
-        unsafe { ffi::notmuch_database_create(path_str.as_ptr(), &mut db) }.as_result()?;
+        let mut error_message = ptr::null_mut();
+        let status = unsafe { ffi::notmuch_database_create_verbose(path_str.as_ptr(), &mut db, &mut error_message) };
+        verbose_result(status, error_message)?;
 
-        Ok(Database {
-            ptr: db,
-        })
+        Ok(Database::from_ptr(db, DatabaseMode::ReadWrite))
     }
 
+    /// Open the existing database for the mail stored under `path`. This
+    /// never creates a database; see `create` for that.
     pub fn open<P>(path: &P, mode: DatabaseMode) -> Result<Self>
     where
         P: AsRef<Path>,
     {
-        let path_str = CString::new(path.as_ref().to_str().unwrap()).unwrap();
+        let path_str = path_to_cstring(path)?;
 
         let mut db = ptr::null_mut();
-        unsafe { ffi::notmuch_database_open(path_str.as_ptr(), mode.into(), &mut db) }
-            .as_result()?;
+        let mut error_message = ptr::null_mut();
+        let status = unsafe {
+            ffi::notmuch_database_open_verbose(path_str.as_ptr(), mode.into(), &mut db, &mut error_message)
+        };
+        verbose_result(status, error_message)?;
+
+        Ok(Database::from_ptr(db, mode))
+    }
+
+    /// Open a database using the configuration file at `config_path` and
+    /// the given configuration `profile`.
+    ///
+    /// Any argument left as `None` is resolved by notmuch as usual: the
+    /// database path from the configuration, the configuration file from
+    /// `$NOTMUCH_CONFIG` and the profile from `$NOTMUCH_PROFILE`.
+    #[cfg(feature = "v0_32")]
+    pub fn open_with_config(
+        path: Option<&Path>,
+        mode: DatabaseMode,
+        config_path: Option<&Path>,
+        profile: Option<&str>,
+    ) -> Result<Self> {
+        let ptr = Self::_with_config(path, config_path, profile, |path, config, profile, db, error_message| unsafe {
+            ffi::notmuch_database_open_with_config(path, mode.into(), config, profile, db, error_message)
+        })?;
+
+        Ok(Database::from_ptr(ptr, mode))
+    }
+
+    /// Create a new database using the configuration file at `config_path`
+    /// and the given configuration `profile`, opened read-write.
+    ///
+    /// Arguments left as `None` are resolved as for `open_with_config`.
+    #[cfg(feature = "v0_32")]
+    pub fn create_with_config(
+        path: Option<&Path>,
+        config_path: Option<&Path>,
+        profile: Option<&str>,
+    ) -> Result<Self> {
+        let ptr = Self::_with_config(path, config_path, profile, |path, config, profile, db, error_message| unsafe {
+            ffi::notmuch_database_create_with_config(path, config, profile, db, error_message)
+        })?;
+
+        Ok(Database::from_ptr(ptr, DatabaseMode::ReadWrite))
+    }
+
+    #[cfg(feature = "v0_32")]
+    fn _with_config<F>(
+        path: Option<&Path>,
+        config_path: Option<&Path>,
+        profile: Option<&str>,
+        f: F,
+    ) -> Result<*mut ffi::notmuch_database_t>
+    where
+        F: FnOnce(
+            *const libc::c_char,
+            *const libc::c_char,
+            *const libc::c_char,
+            *mut *mut ffi::notmuch_database_t,
+            *mut *mut libc::c_char,
+        ) -> ffi::notmuch_status_t,
+    {
+        let path_str = match path {
+            Some(p) => Some(path_to_cstring(p)?),
+            None => None,
+        };
+        let config_str = match config_path {
+            Some(p) => Some(path_to_cstring(p)?),
+            None => None,
+        };
+        let profile_str = match profile {
+            Some(p) => Some(CString::new(p)?),
+            None => None,
+        };
 
-        Ok(Database {
-            ptr: db,
-        })
+        let mut db = ptr::null_mut();
+        let mut error_message = ptr::null_mut();
+        let status = f(
+            path_str.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+            config_str.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+            profile_str.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+            &mut db,
+            &mut error_message,
+        );
+
+        verbose_result(status, error_message)?;
+
+        Ok(db)
     }
 
-    pub fn close(&self) -> Result<()> {
-        unsafe { ffi::notmuch_database_close(self.ptr) }.as_result()?;
+    /// Reopen the database in `mode`, picking up changes other processes
+    /// made since it was opened.
+    #[cfg(feature = "v0_35")]
+    pub fn reopen(&self, mode: DatabaseMode) -> Result<()> {
+        self.status_result(|| unsafe { ffi::notmuch_database_reopen(self.handle.ptr, mode.into()) })?;
+        self.handle.read_only.store(mode == DatabaseMode::ReadOnly, AtomicOrdering::SeqCst);
 
         Ok(())
     }
 
-    pub fn compact<P, F>(path: &P, backup_path: Option<&P>) -> Result<()>
+    /// The mode the database was opened (or last reopened) in.
+    pub fn mode(&self) -> DatabaseMode {
+        if self.handle.read_only.load(AtomicOrdering::SeqCst) {
+            DatabaseMode::ReadOnly
+        } else {
+            DatabaseMode::ReadWrite
+        }
+    }
+
+    /// The number of atomic operations currently open on this handle.
+    pub fn atomic_depth(&self) -> usize {
+        self.handle.atomic_depth.load(AtomicOrdering::SeqCst)
+    }
+
+    /// Fail with `Status::ReadOnlyDatabase` unless the database was opened
+    /// read-write.
+    pub(crate) fn check_writable(&self) -> Result<()> {
+        match self.mode() {
+            DatabaseMode::ReadOnly => Err(Error::NotmuchError(Status::ReadOnlyDatabase, None)),
+            DatabaseMode::ReadWrite => Ok(()),
+        }
+    }
+
+    /// Commit all changes and close the database, reporting any failure
+    /// that dropping it would silently discard.
+    ///
+    /// This closes the database for all clones of the handle; any further
+    /// operation through them fails.
+    pub fn close(self) -> Result<()> {
+        unsafe { ffi::notmuch_database_close(self.handle.ptr) }.as_result()
+    }
+
+    pub fn compact<P>(path: &P, backup_path: Option<&P>) -> Result<()>
     where
         P: AsRef<Path>,
-        F: FnMut(&str),
     {
-        let status: Option<F> = None;
+        let status: Option<fn(&str)> = None;
         Database::_compact(path, backup_path, status)
     }
 
+    /// Like `compact`, calling `status` with each diagnostic and
+    /// informational message notmuch reports along the way.
     pub fn compact_with_status<P, F>(path: &P, backup_path: Option<&P>, status: F) -> Result<()>
     where
         P: AsRef<Path>,
@@ -116,7 +285,7 @@ impl Database {
         Database::_compact(path, backup_path, Some(status))
     }
 
-    fn _compact<P, F>(path: &P, backup_path: Option<&P>, status: Option<F>) -> Result<()>
+    fn _compact<P, F>(path: &P, backup_path: Option<&P>, mut status: Option<F>) -> Result<()>
     where
         P: AsRef<Path>,
         F: FnMut(&str),
@@ -126,49 +295,80 @@ impl Database {
             closure: *mut libc::c_void,
         ) {
             let closure = closure as *mut F;
-            unsafe { (*closure)(message.to_str().unwrap()) }
+            unsafe { (*closure)(&message.to_string_lossy()) }
         }
 
-        let path_str = CString::new(path.as_ref().to_str().unwrap()).unwrap();
+        let path_str = path_to_cstring(path)?;
 
-        let backup_path = backup_path.map(|p| CString::new(p.as_ref().to_str().unwrap()).unwrap());
+        let backup_path = match backup_path {
+            Some(p) => Some(path_to_cstring(p)?),
+            None => None,
+        };
 
         unsafe {
             ffi::notmuch_database_compact(
                 path_str.as_ptr(),
-                backup_path.map_or(ptr::null(), |p| p.as_ptr()),
+                backup_path.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
                 if status.is_some() {
                     Some(wrapper::<F>)
                 } else {
                     None
                 },
-                status.map_or(ptr::null_mut(), |f| &f as *const _ as *mut libc::c_void),
+                status.as_mut().map_or(ptr::null_mut(), |f| f as *mut F as *mut libc::c_void),
             )
         }.as_result()?;
 
         Ok(())
     }
 
+    /// Retrieve the detailed message notmuch recorded for a failed
+    /// operation on this database, if any. notmuch never clears it, so it
+    /// may describe an earlier failure than the last one.
+    pub fn status_string(&self) -> Option<String> {
+        let status = unsafe { ffi::notmuch_database_status_string(self.handle.ptr) };
+        if status.is_null() {
+            None
+        } else {
+            Some(status.to_string_lossy().trim_end().to_string())
+        }
+    }
+
+    /// Turn the status returned by `f` into a `Result`, attaching the
+    /// message notmuch recorded for a failure as its detail. Since notmuch
+    /// never clears the message, it is only attached if `f` changed it.
+    pub(crate) fn status_result<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce() -> ffi::notmuch_status_t,
+    {
+        let before = self.status_string();
+        let status = f();
+        if status.is_ok() {
+            return Ok(());
+        }
+
+        let after = self.status_string();
+        let detail = if after != before { after } else { None };
+        Err(Error::NotmuchError(status.into(), detail))
+    }
+
+    /// Return the path the database was opened or created at.
     pub fn path(&self) -> &Path {
-        Path::new(
-            unsafe { ffi::notmuch_database_get_path(self.ptr) }
-                .to_str()
-                .unwrap(),
-        )
+        unsafe { ffi::notmuch_database_get_path(self.handle.ptr) }.to_path()
     }
 
+    /// Return the version of the database's on-disk format.
     pub fn version(&self) -> u32 {
-        unsafe { ffi::notmuch_database_get_version(self.ptr) }
+        unsafe { ffi::notmuch_database_get_version(self.handle.ptr) }
     }
 
+    /// Return the committed database revision and UUID.
+    ///
+    /// The UUID is copied out of notmuch-owned memory.
     #[cfg(feature = "v0_21")]
     pub fn revision(&self) -> Revision {
-        let uuid_p: *const libc::c_char = ptr::null();
+        let mut uuid_p: *const libc::c_char = ptr::null();
         let revision = unsafe {
-            ffi::notmuch_database_get_revision(
-                self.ptr,
-                (&uuid_p) as *const _ as *mut *const libc::c_char,
-            )
+            ffi::notmuch_database_get_revision(self.handle.ptr, &mut uuid_p)
         };
 
         let uuid = unsafe { CStr::from_ptr(uuid_p) };
@@ -180,48 +380,52 @@ impl Database {
     }
 
     pub fn needs_upgrade(&self) -> bool {
-        unsafe { ffi::notmuch_database_needs_upgrade(self.ptr) == 1 }
+        unsafe { ffi::notmuch_database_needs_upgrade(self.handle.ptr) == 1 }
     }
 
-    pub fn upgrade<F>(&mut self) -> Result<()>
-    where
-        F: FnMut(f64),
-    {
-        let status: Option<F> = None;
+    /// Upgrade the database to the latest supported version. This is a
+    /// no-op if `needs_upgrade` returns `false`.
+    ///
+    /// The database must be opened read-write.
+    pub fn upgrade(&self) -> Result<()> {
+        let status: Option<fn(f64)> = None;
         self._upgrade(status)
     }
 
-    pub fn upgrade_with_status<F>(&mut self, status: F) -> Result<()>
+    /// Like `upgrade`, periodically calling `status` with the progress
+    /// made so far, in the range of [0.0 .. 1.0].
+    pub fn upgrade_with_status<F>(&self, status: F) -> Result<()>
     where
         F: FnMut(f64),
     {
         self._upgrade(Some(status))
     }
 
-    fn _upgrade<F>(&mut self, status: Option<F>) -> Result<()>
+    fn _upgrade<F>(&self, mut status: Option<F>) -> Result<()>
     where
         F: FnMut(f64),
     {
-        #[allow(trivial_numeric_casts)]
+        self.check_writable()?;
+
         extern "C" fn wrapper<F>(closure: *mut libc::c_void, progress: libc::c_double)
         where
             F: FnMut(f64),
         {
             let closure = closure as *mut F;
-            unsafe { (*closure)(progress as f64) }
+            unsafe { (*closure)(progress) }
         }
 
-        unsafe {
+        self.status_result(|| unsafe {
             ffi::notmuch_database_upgrade(
-                self.ptr,
+                self.handle.ptr,
                 if status.is_some() {
                     Some(wrapper::<F>)
                 } else {
                     None
                 },
-                status.map_or(ptr::null_mut(), |f| &f as *const _ as *mut libc::c_void),
+                status.as_mut().map_or(ptr::null_mut(), |f| f as *mut F as *mut libc::c_void),
             )
-        }.as_result()?;
+        })?;
 
         Ok(())
     }
@@ -233,15 +437,139 @@ impl Database {
         <Self as DatabaseExt>::directory(self, path)
     }
 
+    /// Whether the directory at `path` needs to be scanned for changes,
+    /// given its current modification time on disk. This is the case
+    /// unless the database has the same mtime stored for it, as recorded
+    /// with `Directory::set_mtime` after the last scan.
+    pub fn needs_rescan<P>(&self, path: &P, fs_mtime: i64) -> Result<bool>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(match self.directory(path)? {
+            Some(dir) => dir.mtime() != fs_mtime,
+            None => true,
+        })
+    }
+
+    /// Retrieve the value of a configuration key stored in the database.
+    ///
+    /// Returns `Ok(None)` if the key has not been set.
+    pub fn config(&self, key: &str) -> Result<Option<String>> {
+        let key_str = CString::new(key)?;
+
+        let mut value = ptr::null_mut();
+        self.status_result(|| unsafe {
+            ffi::notmuch_database_get_config(self.handle.ptr, key_str.as_ptr(), &mut value)
+        })?;
+
+        if value.is_null() {
+            return Ok(None);
+        }
+
+        let value_str = unsafe { CStr::from_ptr(value) }.to_string_lossy().into_owned();
+        unsafe { libc::free(value as *mut libc::c_void) };
+
+        if value_str.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(value_str))
+        }
+    }
+
+    /// Retrieve the values of a list-valued configuration key such as
+    /// `search.exclude_tags`, as split by notmuch.
+    #[cfg(feature = "v0_32")]
+    pub fn config_values(&self, key: &str) -> Result<Vec<String>> {
+        let key_str = CString::new(key)?;
+
+        let values = unsafe { ffi::notmuch_config_get_values_string(self.handle.ptr, key_str.as_ptr()) };
+        if values.is_null() {
+            return Err(Error::NotmuchError(Status::OutOfMemory, None));
+        }
+
+        let mut result = Vec::new();
+        while unsafe { ffi::notmuch_config_values_valid(values) } != 0 {
+            let value = unsafe { ffi::notmuch_config_values_get(values) };
+            result.push(unsafe { CStr::from_ptr(value) }.to_string_lossy().into_owned());
+            unsafe { ffi::notmuch_config_values_move_to_next(values) };
+        }
+        unsafe { ffi::notmuch_config_values_destroy(values) };
+
+        Ok(result)
+    }
+
+    /// Store a configuration key in the database.
+    pub fn set_config(&self, key: &str, value: &str) -> Result<()> {
+        self.check_writable()?;
+
+        let key_str = CString::new(key)?;
+        let value_str = CString::new(value)?;
+
+        self.status_result(|| unsafe {
+            ffi::notmuch_database_set_config(self.handle.ptr, key_str.as_ptr(), value_str.as_ptr())
+        })
+    }
+
+    /// Iterate over all configuration pairs whose keys start with
+    /// `prefix`. An empty prefix lists everything.
     pub fn config_list<'d>(&'d self, prefix: &str) -> Result<ConfigList<'d>>
     {
         <Self as DatabaseExt>::config_list(self, prefix)
     }
 
+    /// Like `config_list`, but with `prefix` stripped from every key, so
+    /// `maildir.synchronize_flags` listed under `maildir.` comes back as
+    /// `synchronize_flags`.
+    pub fn config_list_suffixes(&self, prefix: &str) -> Result<Vec<(String, String)>> {
+        Ok(self
+            .config_list(prefix)?
+            .map(|(key, value)| {
+                let suffix = key.strip_prefix(prefix).unwrap_or(&key).to_string();
+                (suffix, value)
+            })
+            .collect())
+    }
+
     pub fn create_query<'d>(&'d self, query_string: &str) -> Result<Query<'d>> {
         <Self as DatabaseExt>::create_query(self, query_string)
     }
 
+    /// Create a query, parsing `query_string` with the given `syntax`
+    /// right away.
+    #[cfg(feature = "v0_35")]
+    pub fn create_query_with_syntax<'d>(&'d self, query_string: &str, syntax: QuerySyntax) -> Result<Query<'d>> {
+        <Self as DatabaseExt>::create_query_with_syntax(self, query_string, syntax)
+    }
+
+    /// Search for the messages matching `query_string` with the default
+    /// query settings. The query is kept alive by the returned messages.
+    pub fn search_messages<'d>(&'d self, query_string: &str) -> Result<Messages<'d, Query<'d>>> {
+        <Query as QueryExt>::search_messages(self.create_query(query_string)?)
+    }
+
+    /// Search for the threads matching `query_string` with the default
+    /// query settings. The query is kept alive by the returned threads.
+    pub fn search_threads<'d>(&'d self, query_string: &str) -> Result<Threads<'d, 'd>> {
+        <Query as QueryExt>::search_threads(self.create_query(query_string)?)
+    }
+
+    /// Iterate over every message in the database, i.e. the matches of
+    /// the `*` query.
+    pub fn all_messages<'d>(&'d self) -> Result<Messages<'d, Query<'d>>> {
+        self.search_messages("*")
+    }
+
+    /// Iterate over every thread in the database, i.e. the matches of
+    /// the `*` query.
+    pub fn all_threads<'d>(&'d self) -> Result<Threads<'d, 'd>> {
+        self.search_threads("*")
+    }
+
+    /// Start building a query for `query_string`. See `QueryBuilder`.
+    pub fn query_builder<'d>(&'d self, query_string: &str) -> QueryBuilder<'d> {
+        QueryBuilder::new(self, query_string)
+    }
+
     pub fn all_tags<'d>(&'d self) -> Result<Tags<'d, Self>> {
         <Self as DatabaseExt>::all_tags(self)
     }
@@ -257,31 +585,183 @@ impl Database {
         <Self as DatabaseExt>::find_message_by_filename(self, filename)
     }
 
-    pub fn remove_message<'d, P>(&'d self, path: &P) -> Result<()>
+    /// Remove a message filename from the database, returning whether
+    /// other filenames for the same message ID remain.
+    pub fn remove_message<'d, P>(&'d self, path: &P) -> Result<bool>
     where
         P: AsRef<Path>,
     {
         <Self as DatabaseExt>::remove_message(self, path)
     }
 
-    pub fn default_indexopts<'d, P>(&'d self) -> Result<IndexOpts<'d>>
+    /// Get the indexing options configured for this database, to tweak
+    /// and pass to `index_file` or `Message::reindex`.
+    pub fn default_indexopts<'d>(&'d self) -> Result<IndexOpts<'d>>
     {
         <Self as DatabaseExt>::default_indexopts(self)
     }
 
-    pub fn index_file<'d, P>(&'d self, path: &P, indexopts: Option<IndexOpts<'d>>) -> Result<Message<'d, Self>>
+    /// Add a message file to the database, returning the message and
+    /// whether a message with the same ID was already present.
+    ///
+    /// If the message ID was already known, the file is added as another
+    /// filename of the existing message and the returned flag is `true`.
+    /// The database must be opened read-write; otherwise
+    /// `Status::ReadOnlyDatabase` is returned.
+    pub fn index_file<'d, P>(&'d self, path: &P, indexopts: Option<IndexOpts<'d>>) -> Result<(Message<'d, Self>, bool)>
     where
         P: AsRef<Path>,
     {
         <Self as DatabaseExt>::index_file(self, path, indexopts)
     }
 
-    pub fn begin_atomic(&self) -> Result<()> {
-        unsafe { ffi::notmuch_database_begin_atomic(self.ptr) }.as_result()
+    /// Begin an atomic database operation, which ends when the returned
+    /// guard is dropped or explicitly ended.
+    ///
+    /// Atomic operations may be nested; the changes are committed when the
+    /// outermost one ends. See `atomic_depth`.
+    pub fn begin_atomic(&self) -> Result<AtomicOperation<'_>> {
+        AtomicOperation::new(self)
+    }
+
+    /// Add `tag` to each of `messages` inside a single atomic operation,
+    /// returning how many messages did not have it yet.
+    pub fn add_tag_all<'o, O>(&self, messages: Messages<'o, O>, tag: &str) -> Result<usize>
+    where
+        O: MessageOwner + 'o,
+    {
+        self._tag_all(messages, tag, true)
+    }
+
+    /// Remove `tag` from each of `messages` inside a single atomic
+    /// operation, returning how many messages had it.
+    pub fn remove_tag_all<'o, O>(&self, messages: Messages<'o, O>, tag: &str) -> Result<usize>
+    where
+        O: MessageOwner + 'o,
+    {
+        self._tag_all(messages, tag, false)
+    }
+
+    fn _tag_all<'o, O>(&self, messages: Messages<'o, O>, tag: &str, add: bool) -> Result<usize>
+    where
+        O: MessageOwner + 'o,
+    {
+        self.check_writable()?;
+
+        let atomic = self.begin_atomic()?;
+        let mut changed = 0;
+        for message in messages {
+            if message.tags().contains(tag) != add {
+                if add {
+                    message.add_tag(tag)?;
+                } else {
+                    message.remove_tag(tag)?;
+                }
+                changed += 1;
+            }
+        }
+        atomic.end()?;
+
+        Ok(changed)
+    }
+}
+
+/// Collects the options for opening a database and picks the matching
+/// `notmuch_database_open*` call.
+///
+/// Obtained through `Database::opener`. The mode defaults to `ReadOnly`.
+#[derive(Debug, Clone)]
+pub struct DatabaseOpener {
+    path: Option<PathBuf>,
+    mode: DatabaseMode,
+    config: Option<PathBuf>,
+    profile: Option<String>,
+}
+
+impl DatabaseOpener {
+    pub fn new() -> Self {
+        DatabaseOpener {
+            path: None,
+            mode: DatabaseMode::ReadOnly,
+            config: None,
+            profile: None,
+        }
+    }
+
+    /// The path of the mail store. Without it, the path is taken from the
+    /// configuration, which requires the `v0_32` feature.
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn mode(mut self, mode: DatabaseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// See `Database::open_with_config`.
+    pub fn config<P: AsRef<Path>>(mut self, config: P) -> Self {
+        self.config = Some(config.as_ref().to_path_buf());
+        self
+    }
+
+    /// See `Database::open_with_config`.
+    pub fn profile(mut self, profile: &str) -> Self {
+        self.profile = Some(profile.to_string());
+        self
+    }
+
+    /// Open the database. With only a path set this is `Database::open`;
+    /// otherwise `Database::open_with_config` is used, and without the
+    /// `v0_32` feature `Status::UnsupportedOperation` is returned.
+    pub fn open(self) -> Result<Database> {
+        if self.config.is_none() && self.profile.is_none() {
+            if let Some(ref path) = self.path {
+                return Database::open(path, self.mode);
+            }
+        }
+
+        self._open_with_config()
+    }
+
+    #[cfg(feature = "v0_32")]
+    fn _open_with_config(self) -> Result<Database> {
+        Database::open_with_config(
+            self.path.as_deref(),
+            self.mode,
+            self.config.as_deref(),
+            self.profile.as_deref(),
+        )
+    }
+
+    #[cfg(not(feature = "v0_32"))]
+    fn _open_with_config(self) -> Result<Database> {
+        Err(Error::NotmuchError(Status::UnsupportedOperation, None))
     }
+}
 
-    pub fn end_atomic(&self) -> Result<()> {
-        unsafe { ffi::notmuch_database_end_atomic(self.ptr) }.as_result()
+impl Default for DatabaseOpener {
+    fn default() -> Self {
+        DatabaseOpener::new()
+    }
+}
+
+// Turn the status and the malloc'ed error message returned by notmuch's
+// `*_verbose` and `*_with_config` functions into a `Result`.
+fn verbose_result(status: ffi::notmuch_status_t, error_message: *mut libc::c_char) -> Result<()> {
+    let detail = if error_message.is_null() {
+        None
+    } else {
+        let detail = unsafe { CStr::from_ptr(error_message) }.to_string_lossy().trim_end().to_string();
+        unsafe { libc::free(error_message as *mut libc::c_void) };
+        Some(detail)
+    };
+
+    if status.is_ok() {
+        Ok(())
+    } else {
+        Err(Error::NotmuchError(status.into(), detail))
     }
 }
 
@@ -291,11 +771,27 @@ pub trait DatabaseExt {
         D: Into<Supercow<'d, Database>>,
     {
         let dbref = database.into();
-        let query_str = CString::new(query_string).unwrap();
+        let query_str = CString::new(query_string)?;
+
+        let query = unsafe { ffi::notmuch_query_create(dbref.handle.ptr, query_str.as_ptr()) };
+
+        Ok(Query::from_ptr(query, dbref))
+    }
+
+    #[cfg(feature = "v0_35")]
+    fn create_query_with_syntax<'d, D>(database: D, query_string: &str, syntax: QuerySyntax) -> Result<Query<'d>>
+    where
+        D: Into<Supercow<'d, Database>>,
+    {
+        let dbref = database.into();
+        let query_str = CString::new(query_string)?;
 
-        let query = unsafe { ffi::notmuch_query_create(dbref.ptr, query_str.as_ptr()) };
+        let mut query = ptr::null_mut();
+        dbref.status_result(|| unsafe {
+            ffi::notmuch_query_create_with_syntax(dbref.handle.ptr, query_str.as_ptr(), syntax.into(), &mut query)
+        })?;
 
-        Ok(Query::from_ptr(query, Supercow::phantom(dbref)))
+        Ok(Query::from_ptr(query, dbref))
     }
 
     fn all_tags<'d, D>(database: D) -> Result<Tags<'d, Database>>
@@ -304,7 +800,7 @@ pub trait DatabaseExt {
     {
         let dbref = database.into();
 
-        let tags = unsafe { ffi::notmuch_database_get_all_tags(dbref.ptr) };
+        let tags = unsafe { ffi::notmuch_database_get_all_tags(dbref.handle.ptr) };
 
         Ok(Tags::from_ptr(tags, ScopedSupercow::phantom(dbref)))
     }
@@ -316,17 +812,18 @@ pub trait DatabaseExt {
     {
         let dbref = database.into();
 
-        let path_str = CString::new(path.as_ref().to_str().unwrap()).unwrap();
+        let path_str = path_to_cstring(path)?;
 
         let mut dir = ptr::null_mut();
-        unsafe {
-            ffi::notmuch_database_get_directory(dbref.ptr, path_str.as_ptr(), &mut dir)
-        }.as_result()?;
+        dbref.status_result(|| unsafe {
+            ffi::notmuch_database_get_directory(dbref.handle.ptr, path_str.as_ptr(), &mut dir)
+        })?;
 
         if dir.is_null() {
             Ok(None)
         } else {
-            Ok(Some(Directory::from_ptr(dir, Supercow::phantom(dbref))))
+            let database = Database::clone(&dbref);
+            Ok(Some(Directory::from_ptr(dir, Supercow::phantom(dbref), database)))
         }
     }
 
@@ -336,12 +833,12 @@ pub trait DatabaseExt {
     {
         let dbref = database.into();
 
-        let prefix_str = CString::new(prefix).unwrap();
+        let prefix_str = CString::new(prefix)?;
 
         let mut cfgs = ptr::null_mut();
-        unsafe {
-            ffi::notmuch_database_get_config_list(dbref.ptr, prefix_str.as_ptr(), &mut cfgs)
-        }.as_result()?;
+        dbref.status_result(|| unsafe {
+            ffi::notmuch_database_get_config_list(dbref.handle.ptr, prefix_str.as_ptr(), &mut cfgs)
+        })?;
 
         Ok(ConfigList::from_ptr(cfgs, Supercow::phantom(dbref)))
     }
@@ -351,17 +848,18 @@ pub trait DatabaseExt {
         D: Into<ScopedSupercow<'d, Database>>
     {
         let dbref = database.into();
-        let message_id_str = CString::new(message_id).unwrap();
+        let message_id_str = CString::new(message_id)?;
 
         let mut msg = ptr::null_mut();
-        unsafe {
-            ffi::notmuch_database_find_message(dbref.ptr, message_id_str.as_ptr(), &mut msg)
-        }.as_result()?;
+        dbref.status_result(|| unsafe {
+            ffi::notmuch_database_find_message(dbref.handle.ptr, message_id_str.as_ptr(), &mut msg)
+        })?;
 
         if msg.is_null() {
             Ok(None)
         } else {
-            Ok(Some(Message::from_ptr(msg, Supercow::phantom(dbref))))
+            let database = Database::clone(&dbref);
+            Ok(Some(Message::from_ptr(msg, Supercow::phantom(dbref), database)))
         }
     }
 
@@ -371,35 +869,38 @@ pub trait DatabaseExt {
         P: AsRef<Path>
     {
         let dbref = database.into();
-        let path_str = CString::new(filename.as_ref().to_str().unwrap()).unwrap();
+        let path_str = path_to_cstring(filename)?;
 
         let mut msg = ptr::null_mut();
-        unsafe {
-            ffi::notmuch_database_find_message_by_filename(dbref.ptr, path_str.as_ptr(), &mut msg)
-        }.as_result()?;
+        dbref.status_result(|| unsafe {
+            ffi::notmuch_database_find_message_by_filename(dbref.handle.ptr, path_str.as_ptr(), &mut msg)
+        })?;
 
         if msg.is_null() {
             Ok(None)
         } else {
-            Ok(Some(Message::from_ptr(msg, Supercow::phantom(dbref))))
+            let database = Database::clone(&dbref);
+            Ok(Some(Message::from_ptr(msg, Supercow::phantom(dbref), database)))
         }
     }
 
-    fn remove_message<'d, D, P>(database: D, path: &P) -> Result<()>
+    fn remove_message<'d, D, P>(database: D, path: &P) -> Result<bool>
     where
         D: Into<ScopedSupercow<'d, Database>>,
         P: AsRef<Path>,
     {
         let dbref = database.into();
-        match path.as_ref().to_str() {
-            Some(path_str) => {
-                let msg_path = CString::new(path_str).unwrap();
+        dbref.check_writable()?;
+        let msg_path = path_to_cstring(path)?;
 
-                unsafe { ffi::notmuch_database_remove_message(dbref.ptr, msg_path.as_ptr()) }
-                    .as_result()
-            }
-            None => Err(Error::NotmuchError(Status::FileError)),
-        }
+        let mut duplicate = false;
+        dbref.status_result(|| {
+            let status = unsafe { ffi::notmuch_database_remove_message(dbref.handle.ptr, msg_path.as_ptr()) };
+            duplicate = status.as_status() == Status::DuplicateMessageID;
+            if duplicate { ffi::notmuch_status_t::NOTMUCH_STATUS_SUCCESS } else { status }
+        })?;
+
+        Ok(duplicate)
     }
 
     fn default_indexopts<'d, D>(database: D) -> Result<IndexOpts<'d>>
@@ -408,45 +909,54 @@ pub trait DatabaseExt {
     {
         let dbref = database.into();
 
-        let opts = unsafe { ffi::notmuch_database_get_default_indexopts(dbref.ptr) };
+        let opts = unsafe { ffi::notmuch_database_get_default_indexopts(dbref.handle.ptr) };
+        if opts.is_null() {
+            return Err(Error::NotmuchError(Status::OutOfMemory, None));
+        }
 
-        Ok(IndexOpts::from_ptr(opts, ScopedSupercow::phantom(dbref)))
+        let db = dbref.handle.ptr;
+        Ok(IndexOpts::from_ptr(opts, db, ScopedSupercow::phantom(dbref)))
     }
 
 
-    fn index_file<'d, D, P>(database: D, path: &P, indexopts: Option<IndexOpts<'d>>) -> Result<Message<'d, Database>>
+    fn index_file<'d, D, P>(database: D, path: &P, indexopts: Option<IndexOpts<'d>>) -> Result<(Message<'d, Database>, bool)>
     where
         D: Into<ScopedSupercow<'d, Database>>,
         P: AsRef<Path>,
     {
         let dbref = database.into();
+        dbref.check_writable()?;
 
-        let opts = indexopts.map_or(ptr::null_mut(), |opt| opt.ptr);
+        // keep the options alive until notmuch is done with them
+        let opts = indexopts.as_ref().map_or(ptr::null_mut(), |opt| opt.ptr);
 
-        match path.as_ref().to_str() {
-            Some(path_str) => {
-                let msg_path = CString::new(path_str).unwrap();
+        let msg_path = path_to_cstring(path)?;
 
-                let mut msg = ptr::null_mut();
-                unsafe { ffi::notmuch_database_index_file(dbref.ptr, msg_path.as_ptr(), opts, &mut msg) }
-                    .as_result()?;
-                
-                Ok(Message::from_ptr(msg, ScopedSupercow::phantom(dbref)))
-            }
-            None => Err(Error::NotmuchError(Status::FileError)),
-        }
+        let mut msg = ptr::null_mut();
+        let mut duplicate = false;
+        dbref.status_result(|| {
+            let status = unsafe { ffi::notmuch_database_index_file(dbref.handle.ptr, msg_path.as_ptr(), opts, &mut msg) };
+            duplicate = status.as_status() == Status::DuplicateMessageID;
+            if duplicate { ffi::notmuch_status_t::NOTMUCH_STATUS_SUCCESS } else { status }
+        })?;
+
+        let database = Database::clone(&dbref);
+        Ok((Message::from_ptr(msg, ScopedSupercow::phantom(dbref), database), duplicate))
     }
 }
 
 impl DatabaseExt for Database {}
 
 unsafe impl Send for Database {}
-unsafe impl Sync for Database {}
+// Only for the `Arc`'s sake; `Database` itself is not `Sync`.
+unsafe impl Send for DatabaseHandle {}
+unsafe impl Sync for DatabaseHandle {}
 
 
 #[derive(Debug)]
 pub struct AtomicOperation<'d> {
     database: ScopedSupercow<'d, Database>,
+    ended: bool,
 }
 
 impl<'d> AtomicOperation<'d> {
@@ -455,16 +965,40 @@ impl<'d> AtomicOperation<'d> {
         D: Into<ScopedSupercow<'d, Database>>,
     {
         let database = db.into();
-        database.begin_atomic()?;
+        database.check_writable()?;
+        database.status_result(|| unsafe { ffi::notmuch_database_begin_atomic(database.handle.ptr) })?;
+        database.handle.atomic_depth.fetch_add(1, AtomicOrdering::SeqCst);
         Ok(AtomicOperation{
-            database
+            database,
+            ended: false,
         })
     }
+
+    /// End the atomic operation, reporting any error that dropping the
+    /// guard would otherwise discard.
+    pub fn end(mut self) -> Result<()> {
+        self._end()
+    }
+
+    // Runs exactly once per guard, from `end` or from `drop`.
+    fn _end(&mut self) -> Result<()> {
+        self.ended = true;
+
+        // Every guard accounts for one level, so this can only fail if the
+        // handle's atomic operations were ended behind the guards' backs.
+        let depth = &self.database.handle.atomic_depth;
+        if depth.fetch_update(AtomicOrdering::SeqCst, AtomicOrdering::SeqCst, |d| d.checked_sub(1)).is_err() {
+            return Err(Error::NotmuchError(Status::UnbalancedAtomic, None));
+        }
+
+        self.database.status_result(|| unsafe { ffi::notmuch_database_end_atomic(self.database.handle.ptr) })
+    }
 }
 
 impl<'d> Drop for AtomicOperation<'d> {
     fn drop(&mut self) {
-        let _ = self.database.end_atomic();
+        if !self.ended {
+            let _ = self._end();
+        }
     }
 }
-
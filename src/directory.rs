@@ -1,6 +1,9 @@
 use std::ops::Drop;
+use std::ptr;
 use supercow::Supercow;
+use libc;
 
+use error::Result;
 use ffi;
 use Database;
 use Filenames;
@@ -12,33 +15,83 @@ use utils::{ScopedSupercow, ScopedPhantomcow};
 pub struct Directory<'d> {
     ptr: *mut ffi::notmuch_directory_t,
     marker: ScopedPhantomcow<'d, Database>,
+    database: Database,
 }
 
 impl<'d> Drop for Directory<'d> {
     fn drop(&mut self) {
-        unsafe { ffi::notmuch_directory_destroy(self.ptr) };
+        if !self.ptr.is_null() {
+            unsafe { ffi::notmuch_directory_destroy(self.ptr) };
+        }
     }
 }
 
 impl<'d> FilenamesOwner for Directory<'d> {}
 
 impl<'d> Directory<'d> {
-    pub(crate) fn from_ptr<O>(ptr: *mut ffi::notmuch_directory_t, owner: O) -> Directory<'d>
+    pub(crate) fn from_ptr<O>(ptr: *mut ffi::notmuch_directory_t, owner: O, database: Database) -> Directory<'d>
     where
         O: Into<ScopedPhantomcow<'d, Database>>,
     {
         Directory {
             ptr,
             marker: owner.into(),
+            database,
         }
     }
 
+    /// Get the modification time stored for the directory, as a time_t
+    /// value. Returns 0 if no mtime has been stored.
+    pub fn mtime(&self) -> i64 {
+        unsafe { ffi::notmuch_directory_get_mtime(self.ptr) as i64 }
+    }
+
+    /// Store the modification time of the directory, as a time_t value.
+    ///
+    /// The database must be opened read-write.
+    pub fn set_mtime(&self, mtime: i64) -> Result<()> {
+        self.database.check_writable()?;
+        unsafe { ffi::notmuch_directory_set_mtime(self.ptr, mtime as libc::time_t) }.as_result()
+    }
+
+    /// Delete the directory document from the database.
+    ///
+    /// Any child directories and files are expected to have been removed
+    /// from the database already; their messages are not touched.
+    pub fn delete(mut self) -> Result<()> {
+        self.database.check_writable()?;
+        let status = unsafe { ffi::notmuch_directory_delete(self.ptr) };
+
+        // notmuch destroys the directory, except when refusing to touch a
+        // read-only database.
+        if status != ffi::notmuch_status_t::NOTMUCH_STATUS_READ_ONLY_DATABASE {
+            self.ptr = ptr::null_mut();
+        }
+
+        status.as_result()
+    }
+
+    pub fn child_files(&self) -> Filenames<'_, Self> {
+        <Self as DirectoryExt>::child_files(self)
+    }
+
     pub fn child_directories(&self) -> Filenames<Self> {
         <Self as DirectoryExt>::child_directories(self)
     }
 }
 
 pub trait DirectoryExt<'d> {
+    fn child_files<'s, S>(directory: S) -> Filenames<'s, Directory<'d>>
+    where
+        S: Into<ScopedSupercow<'s, Directory<'d>>>,
+    {
+        let dir = directory.into();
+        Filenames::from_ptr(
+            unsafe { ffi::notmuch_directory_get_child_files(dir.ptr) },
+            Supercow::phantom(dir),
+        )
+    }
+
     fn child_directories<'s, S>(directory: S) -> Filenames<'s, Directory<'d>>
     where
         S: Into<ScopedSupercow<'s, Directory<'d>>>,
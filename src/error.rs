@@ -1,36 +1,63 @@
 use std;
 use std::{error, fmt, io, result};
+use std::ffi::NulError;
 
 use ffi;
 
 pub type Result<T> = result::Result<T, Error>;
 
+/// Errors returned by this crate.
+///
+/// Failures reported by notmuch itself carry the status code, so callers
+/// can match on the specific cause, e.g.
+/// `Err(Error::NotmuchError(Status::ReadOnlyDatabase, _))`. Where notmuch
+/// explains a failure, e.g. when opening a database or on a Xapian
+/// exception during a search, the explanation is kept as well, see
+/// `Error::detail`.
 #[derive(Debug)]
 pub enum Error {
     IoError(io::Error),
-    NotmuchError(ffi::Status),
+    NulError(NulError),
+    NotmuchError(ffi::Status, Option<String>),
     UnspecifiedError,
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", error::Error::description(self))
+impl Error {
+    /// The notmuch status code behind this error, if any.
+    pub fn status(&self) -> Option<ffi::Status> {
+        match *self {
+            Error::NotmuchError(status, _) => Some(status),
+            _ => None,
+        }
+    }
+
+    /// The detailed message notmuch gave along with the status, if any.
+    pub fn detail(&self) -> Option<&str> {
+        match *self {
+            Error::NotmuchError(_, Some(ref detail)) => Some(detail),
+            _ => None,
+        }
     }
 }
 
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::IoError(e) => error::Error::description(e),
-            Error::NotmuchError(e) => e.description(),
-            Error::UnspecifiedError => "Generic notmuch error",
+            Error::IoError(e) => write!(f, "{}", e),
+            Error::NulError(e) => write!(f, "Interior nul byte in string argument at position {}", e.nul_position()),
+            Error::NotmuchError(e, None) => write!(f, "{}", e),
+            Error::NotmuchError(e, Some(detail)) => write!(f, "{}: {}", e, detail),
+            Error::UnspecifiedError => write!(f, "Generic notmuch error"),
         }
     }
+}
 
-    fn cause(&self) -> Option<&dyn error::Error> {
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             Error::IoError(ref e) => Some(e),
-            Error::NotmuchError(ref e) => Some(e),
+            Error::NulError(ref e) => Some(e),
+            Error::NotmuchError(ref e, _) => Some(e),
             Error::UnspecifiedError => None,
         }
     }
@@ -42,14 +69,20 @@ impl std::convert::From<io::Error> for Error {
     }
 }
 
+impl std::convert::From<NulError> for Error {
+    fn from(err: NulError) -> Error {
+        Error::NulError(err)
+    }
+}
+
 impl std::convert::From<ffi::Status> for Error {
     fn from(err: ffi::Status) -> Error {
-        Error::NotmuchError(err)
+        Error::NotmuchError(err, None)
     }
 }
 
 impl std::convert::From<ffi::notmuch_status_t> for Error {
     fn from(err: ffi::notmuch_status_t) -> Error {
-        Error::NotmuchError(ffi::Status::from(err))
+        Error::NotmuchError(ffi::Status::from(err), None)
     }
 }
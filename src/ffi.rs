@@ -26,6 +26,18 @@ notmuch_enum! {
         NOTMUCH_STATUS_UNBALANCED_ATOMIC => UnbalancedAtomic,
         NOTMUCH_STATUS_UNSUPPORTED_OPERATION => UnsupportedOperation,
         NOTMUCH_STATUS_UPGRADE_REQUIRED => UpgradeRequired,
+        NOTMUCH_STATUS_PATH_ERROR => PathError,
+        NOTMUCH_STATUS_IGNORED => Ignored,
+        NOTMUCH_STATUS_ILLEGAL_ARGUMENT => IllegalArgument,
+        NOTMUCH_STATUS_MALFORMED_CRYPTO_PROTOCOL => MalformedCryptoProtocol,
+        NOTMUCH_STATUS_FAILED_CRYPTO_CONTEXT_CREATION => FailedCryptoContextCreation,
+        NOTMUCH_STATUS_UNKNOWN_CRYPTO_PROTOCOL => UnknownCryptoProtocol,
+        NOTMUCH_STATUS_NO_CONFIG => NoConfig,
+        NOTMUCH_STATUS_NO_DATABASE => NoDatabase,
+        NOTMUCH_STATUS_DATABASE_EXISTS => DatabaseExists,
+        NOTMUCH_STATUS_BAD_QUERY_SYNTAX => BadQuerySyntax,
+        NOTMUCH_STATUS_NO_MAIL_ROOT => NoMailRoot,
+        NOTMUCH_STATUS_CLOSED_DATABASE => ClosedDatabase,
         // Not an actual status value.  Just a way to find out how many
         // valid status values there are.
         NOTMUCH_STATUS_LAST_STATUS => LastStatus
@@ -44,11 +56,17 @@ impl notmuch_status_t {
         !self.is_ok()
     }
 
+    /// The exact status code, for callers that treat some failures, such
+    /// as `Status::DuplicateMessageID`, as success.
+    pub fn as_status(self) -> Status {
+        Status::from(self)
+    }
+
     pub fn as_result(self) -> Result<()> {
         if self.is_ok() {
             Ok(())
         } else {
-            Err(Error::NotmuchError(Status::from(self)))
+            Err(Error::NotmuchError(self.as_status(), None))
         }
     }
 }
@@ -69,7 +87,7 @@ impl ToStr for Status {
 
 impl fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.to_str().unwrap())
+        write!(f, "{}", self.to_string_lossy())
     }
 }
 
@@ -131,6 +149,15 @@ notmuch_enum! {
     }
 }
 
+notmuch_enum! {
+    #[repr(C)]
+    #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+    pub enum notmuch_query_syntax_t => QuerySyntax {
+        NOTMUCH_QUERY_SYNTAX_XAPIAN => Xapian,
+        NOTMUCH_QUERY_SYNTAX_SEXP => Sexp
+    }
+}
+
 #[repr(C)]
 pub struct notmuch_database_t(c_void);
 #[repr(C)]
@@ -154,6 +181,8 @@ pub struct notmuch_message_properties_t(c_void);
 #[repr(C)]
 pub struct notmuch_config_list_t(c_void);
 #[repr(C)]
+pub struct notmuch_config_values_t(c_void);
+#[repr(C)]
 pub struct notmuch_indexopts_t(c_void);
 
 pub type notmuch_compact_status_cb_t = extern "C" fn(message: *const c_char, closure: *mut c_void);
@@ -214,7 +243,7 @@ extern "C" {
     pub fn notmuch_database_create_verbose(
         path: *const c_char,
         database: *mut *mut notmuch_database_t,
-        error_message: *mut *const c_char,
+        error_message: *mut *mut c_char,
     ) -> notmuch_status_t;
 
     /// Open an existing notmuch database located at 'path'.
@@ -263,6 +292,60 @@ extern "C" {
         error_message: *mut *mut c_char,
     ) -> notmuch_status_t;
 
+    /// Open an existing notmuch database located at 'database_path', using
+    /// configuration in 'config_path'.
+    ///
+    /// If 'database_path' is NULL, the path is taken from the configuration,
+    /// falling back to `$NOTMUCH_DATABASE` and the default locations.
+    ///
+    /// If 'config_path' is NULL, `$NOTMUCH_CONFIG` is consulted, falling
+    /// back to `$HOME/.notmuch-config`. If it is "", no configuration file
+    /// is loaded.
+    ///
+    /// If 'profile' is NULL, `$NOTMUCH_PROFILE` is used; otherwise it
+    /// selects the configuration file suffix `.<profile>`.
+    ///
+    /// The error message, if any, is allocated by malloc and should be freed
+    /// by the caller.
+    ///
+    /// @since libnotmuch 5.4 (notmuch 0.32)
+    pub fn notmuch_database_open_with_config(
+        database_path: *const c_char,
+        mode: notmuch_database_mode_t,
+        config_path: *const c_char,
+        profile: *const c_char,
+        database: *mut *mut notmuch_database_t,
+        error_message: *mut *mut c_char,
+    ) -> notmuch_status_t;
+
+    /// Create a new notmuch database located at 'database_path', using
+    /// configuration in 'config_path'.
+    ///
+    /// The paths and 'profile' are resolved as for
+    /// `notmuch_database_open_with_config`. The new database is opened
+    /// read-write.
+    ///
+    /// The error message, if any, is allocated by malloc and should be freed
+    /// by the caller.
+    ///
+    /// @since libnotmuch 5.4 (notmuch 0.32)
+    pub fn notmuch_database_create_with_config(
+        database_path: *const c_char,
+        config_path: *const c_char,
+        profile: *const c_char,
+        database: *mut *mut notmuch_database_t,
+        error_message: *mut *mut c_char,
+    ) -> notmuch_status_t;
+
+    /// Reopen an open notmuch database, e.g. to see changes made by other
+    /// processes, or to switch between read-only and read-write mode.
+    ///
+    /// @since libnotmuch 5.6 (notmuch 0.35)
+    pub fn notmuch_database_reopen(
+        db: *mut notmuch_database_t,
+        mode: notmuch_database_mode_t,
+    ) -> notmuch_status_t;
+
     /// Retrieve last status string for given database.
     pub fn notmuch_database_status_string(notmuch: *mut notmuch_database_t) -> *const c_char;
 
@@ -636,6 +719,21 @@ extern "C" {
         query_string: *const c_char,
     ) -> *mut notmuch_query_t;
 
+    /// Create a new query for 'database', parsing 'query_string' with the
+    /// given 'syntax'.
+    ///
+    /// Unlike `notmuch_query_create`, the query string is parsed
+    /// immediately, and NOTMUCH_STATUS_BAD_QUERY_SYNTAX is returned if it
+    /// is invalid.
+    ///
+    /// @since libnotmuch 5.5 (notmuch 0.35)
+    pub fn notmuch_query_create_with_syntax(
+        database: *mut notmuch_database_t,
+        query_string: *const c_char,
+        syntax: notmuch_query_syntax_t,
+        output: *mut *mut notmuch_query_t,
+    ) -> notmuch_status_t;
+
     /// Return the query_string of this query. See `notmuch_query_create`.
     pub fn notmuch_query_get_query_string(query: *mut notmuch_query_t) -> *const c_char;
 
@@ -868,6 +966,22 @@ extern "C" {
         count: *mut c_uint,
     ) -> notmuch_status_t;
 
+    /// Deprecated alias for `notmuch_query_count_messages`.
+    ///
+    /// @since libnotmuch 4.3 (notmuch 0.21)
+    pub fn notmuch_query_count_messages_st(
+        query: *mut notmuch_query_t,
+        count: *mut c_uint,
+    ) -> notmuch_status_t;
+
+    /// Deprecated alias for `notmuch_query_count_threads`.
+    ///
+    /// @since libnotmuch 4.3 (notmuch 0.21)
+    pub fn notmuch_query_count_threads_st(
+        query: *mut notmuch_query_t,
+        count: *mut c_uint,
+    ) -> notmuch_status_t;
+
     /// Get the thread ID of 'thread'.
     ///
     /// The returned string belongs to 'thread' and as such, should not be
@@ -1122,6 +1236,11 @@ extern "C" {
         message: *mut notmuch_message_t,
     ) -> *mut notmuch_filenames_t;
 
+    /// Get the database associated with this message.
+    ///
+    /// @since libnotmuch 5.5 (notmuch 0.34)
+    pub fn notmuch_message_get_database(message: *const notmuch_message_t) -> *mut notmuch_database_t;
+
     /// Re-index the e-mail corresponding to 'message' using the supplied index options
     ///
     /// Returns the status of the re-index operation.  (see the return
@@ -1141,6 +1260,18 @@ extern "C" {
         flag: notmuch_message_flag_t,
     ) -> notmuch_bool_t;
 
+    /// Get a value of a flag for the email corresponding to 'message'.
+    ///
+    /// Returns a status value and stores the value of the flag in
+    /// *is_set.
+    ///
+    /// @since libnotmuch 5.5 (notmuch 0.34)
+    pub fn notmuch_message_get_flag_st(
+        message: *mut notmuch_message_t,
+        flag: notmuch_message_flag_t,
+        is_set: *mut notmuch_bool_t,
+    ) -> notmuch_status_t;
+
     /// Set a value of a flag for the email corresponding to 'message'.
     pub fn notmuch_message_set_flag(
         message: *mut notmuch_message_t,
@@ -1796,6 +1927,45 @@ extern "C" {
     /// @since libnotmuch 4.4 (notmuch 0.23)
     pub fn notmuch_config_list_destroy(config_list: *mut notmuch_config_list_t);
 
+    /// Returns an iterator for a ';'-delimited list of configuration values
+    ///
+    /// These values reflect all configuration information given at the
+    /// time the database was opened.
+    ///
+    /// @returns NULL in case of error.
+    ///
+    /// @since libnotmuch 5.4 (notmuch 0.32)
+    pub fn notmuch_config_get_values_string(
+        notmuch: *mut notmuch_database_t,
+        key: *const c_char,
+    ) -> *mut notmuch_config_values_t;
+
+    /// Is the given 'config_values' iterator pointing at a valid element.
+    ///
+    /// @since libnotmuch 5.4 (notmuch 0.32)
+    pub fn notmuch_config_values_valid(values: *mut notmuch_config_values_t) -> notmuch_bool_t;
+
+    /// Get the current value from the 'values' iterator
+    ///
+    /// @since libnotmuch 5.4 (notmuch 0.32)
+    pub fn notmuch_config_values_get(values: *mut notmuch_config_values_t) -> *const c_char;
+
+    /// Move the 'values' iterator to the next element
+    ///
+    /// @since libnotmuch 5.4 (notmuch 0.32)
+    pub fn notmuch_config_values_move_to_next(values: *mut notmuch_config_values_t);
+
+    /// reset the 'values' iterator to the first element
+    ///
+    /// @since libnotmuch 5.4 (notmuch 0.32)
+    pub fn notmuch_config_values_start(values: *mut notmuch_config_values_t);
+
+    /// Destroy a config values iterator, along with any associated
+    /// resources.
+    ///
+    /// @since libnotmuch 5.4 (notmuch 0.32)
+    pub fn notmuch_config_values_destroy(values: *mut notmuch_config_values_t);
+
     /// get the current default indexing options for a given database.
     ///
     /// This object will survive until the database itself is destroyed,
@@ -1850,3 +2020,21 @@ extern "C" {
     /// @since libnotmuch 4.4 (notmuch 0.23)
     pub fn notmuch_built_with(name: *const c_char) -> notmuch_bool_t;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn as_status() {
+        let status = notmuch_status_t::NOTMUCH_STATUS_DUPLICATE_MESSAGE_ID;
+        assert_eq!(status.as_status(), Status::DuplicateMessageID);
+        assert!(status.is_err());
+
+        match status.as_result() {
+            Err(Error::NotmuchError(Status::DuplicateMessageID, _)) => (),
+            other => panic!("expected DuplicateMessageID, got {:?}", other),
+        }
+        assert!(notmuch_status_t::NOTMUCH_STATUS_SUCCESS.as_result().is_ok());
+    }
+}
@@ -1,6 +1,10 @@
 use std::ffi::CStr;
+#[cfg(unix)]
+use std::ffi::OsStr;
 use std::iter::Iterator;
 use std::ops::Drop;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
 use std::path::PathBuf;
 
 use ffi;
@@ -39,6 +43,13 @@ where
             marker: owner.into(),
         }
     }
+
+    /// Collect the remaining filenames that still exist on disk, in the
+    /// order notmuch yields them. The names of a `Directory`'s children
+    /// are relative, so this is meant for the filenames of messages.
+    pub fn into_existing(self) -> Vec<PathBuf> {
+        self.filter(|f| f.exists()).collect()
+    }
 }
 
 impl<'o, O> Iterator for Filenames<'o, O>
@@ -54,13 +65,19 @@ where
             return None;
         }
 
-        let ctag = unsafe {
+        let cname = unsafe {
             let t = ffi::notmuch_filenames_get(self.ptr);
             ffi::notmuch_filenames_move_to_next(self.ptr);
             CStr::from_ptr(t)
         };
 
-        Some(PathBuf::from(ctag.to_str().unwrap()))
+        // Filenames are raw bytes and need not be valid UTF-8.
+        #[cfg(unix)]
+        let filename = PathBuf::from(OsStr::from_bytes(cname.to_bytes()));
+        #[cfg(not(unix))]
+        let filename = PathBuf::from(cname.to_string_lossy().into_owned());
+
+        Some(filename)
     }
 }
 
@@ -1,8 +1,9 @@
+use std::cell::RefCell;
 use std::ops::Drop;
 
-use error::Result;
+use error::{Error, Result};
 use ffi;
-use ffi::DecryptionPolicy;
+use ffi::{DecryptionPolicy, Status};
 use Database;
 use utils::ScopedPhantomcow;
 
@@ -10,7 +11,8 @@ use utils::ScopedPhantomcow;
 #[derive(Debug)]
 pub struct IndexOpts<'d> {
     pub(crate) ptr: *mut ffi::notmuch_indexopts_t,
-    marker: ScopedPhantomcow<'d, Database>,
+    database: *mut ffi::notmuch_database_t,
+    marker: RefCell<ScopedPhantomcow<'d, Database>>,
 }
 
 impl<'d> Drop for IndexOpts<'d> {
@@ -20,23 +22,57 @@ impl<'d> Drop for IndexOpts<'d> {
 }
 
 impl<'d> IndexOpts<'d> {
-    pub(crate) fn from_ptr<O>(ptr: *mut ffi::notmuch_indexopts_t, owner: O) -> IndexOpts<'d>
+    pub(crate) fn from_ptr<O>(
+        ptr: *mut ffi::notmuch_indexopts_t,
+        database: *mut ffi::notmuch_database_t,
+        owner: O,
+    ) -> IndexOpts<'d>
     where
         O: Into<ScopedPhantomcow<'d, Database>>,
     {
         IndexOpts {
             ptr,
-            marker: owner.into(),
+            database,
+            marker: RefCell::new(owner.into()),
         }
     }
 
-    pub fn set_decrypt_policy(self: &Self, decrypt_policy: DecryptionPolicy) -> Result<()> {
-        unsafe { ffi::notmuch_indexopts_set_decrypt_policy(self.ptr, decrypt_policy.into()) }.as_result()
+    /// Start from the default index options of `database`.
+    pub fn new(database: &'d Database) -> Result<IndexOpts<'d>> {
+        database.default_indexopts()
     }
 
+    /// Specify whether to decrypt encrypted parts while indexing, and
+    /// whether to stash the session keys for later use.
+    pub fn set_decrypt_policy(self: &Self, decrypt_policy: DecryptionPolicy) -> Result<&Self> {
+        unsafe { ffi::notmuch_indexopts_set_decrypt_policy(self.ptr, decrypt_policy.into()) }.as_result()?;
+        Ok(self)
+    }
+
+    /// Return the decryption policy of these options. See
+    /// `set_decrypt_policy`.
     pub fn decrypt_policy(self: &Self) -> DecryptionPolicy {
         unsafe { ffi::notmuch_indexopts_get_decrypt_policy(self.ptr)}.into()
     }
+
+    /// Copy these options into a fresh set that can be changed
+    /// independently. The copy starts from the database defaults again and
+    /// takes over the settings of `self`.
+    pub fn try_clone(&self) -> Result<IndexOpts<'d>> {
+        let ptr = unsafe { ffi::notmuch_database_get_default_indexopts(self.database) };
+        if ptr.is_null() {
+            return Err(Error::NotmuchError(Status::OutOfMemory, None));
+        }
+
+        let opts = IndexOpts::from_ptr(
+            ptr,
+            self.database,
+            // will never panic since the borrow is released immediately
+            ScopedPhantomcow::<'d, Database>::share(&mut *(self.marker.borrow_mut())),
+        );
+        opts.set_decrypt_policy(self.decrypt_policy())?;
+        Ok(opts)
+    }
 }
 
 unsafe impl<'d> Send for IndexOpts<'d> {}
@@ -6,6 +6,12 @@ mod macros;
 
 extern crate libc;
 extern crate supercow;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "time")]
+extern crate time;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 mod ffi;
 mod utils;
@@ -23,21 +29,23 @@ mod threads;
 mod index;
 mod config_list;
 mod message_properties;
+mod capabilities;
 
-pub use database::{Database, DatabaseExt, AtomicOperation};
+pub use database::{Database, DatabaseExt, DatabaseOpener, AtomicOperation};
 pub use directory::{Directory, DirectoryExt};
 pub use error::Error;
 pub use filenames::{Filenames, FilenamesOwner};
-pub use message::{Message, MessageExt, MessageOwner, FrozenMessage};
+pub use message::{Message, MessageExt, MessageOwner, FrozenMessage, OwnedMessage, TagBatch, TransientMessage};
 pub use messages::{Messages, MessagesExt};
 pub use message_properties::{MessageProperties};
-pub use query::{Query, QueryExt};
+pub use query::{Query, QueryBuilder, QueryExt};
 pub use tags::{Tags, TagsExt, TagsOwner};
 pub use thread::{Thread, ThreadExt};
 pub use threads::{Threads, ThreadsExt};
 pub use index::IndexOpts;
 pub use config_list::ConfigList;
+pub use capabilities::{built_features, built_with, capabilities, Capabilities};
 
-pub use ffi::{Status, DatabaseMode, Sort, DecryptionPolicy};
+pub use ffi::{Status, DatabaseMode, Sort, Exclude, DecryptionPolicy, MessageFlag, QuerySyntax};
 
 pub use utils::{ScopedSupercow, ScopedPhantomcow};
\ No newline at end of file
@@ -1,24 +1,43 @@
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
+use std::fs;
 use std::path::PathBuf;
 use std::cell::RefCell;
 use std::borrow::Cow;
 use std::ptr;
+use std::ops::Deref;
+use std::hash::{Hash, Hasher};
+use std::fmt;
 
 use supercow::{Supercow};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "time")]
+use time::OffsetDateTime;
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, Serializer, SerializeStruct};
 
 use error::{Error, Result};
 use ffi;
-use utils::{ToStr, ScopedPhantomcow, ScopedSupercow};
+use ffi::{Status, MessageFlag};
+use utils::{ToStr, ToPath, ScopedPhantomcow, ScopedSupercow};
 use Filenames;
 use FilenamesOwner;
 use Messages;
 use MessageProperties;
 use Tags;
 use TagsOwner;
+use Thread;
 use IndexOpts;
+use Database;
+use DatabaseExt;
 
 pub trait MessageOwner: Send + Sync {}
 
+/// A message holding on to its database rather than borrowing it from a
+/// query, so that it can be stored and sent around freely. See
+/// `Message::into_owned`.
+pub type OwnedMessage = Message<'static, Database>;
+
 #[derive(Debug)]
 pub struct Message<'o, O>
 where
@@ -26,6 +45,7 @@ where
 {
     pub(crate) ptr: *mut ffi::notmuch_message_t,
     marker: RefCell<ScopedPhantomcow<'o, O>>,
+    database: Database,
 }
 
 impl<'o, O> MessageOwner for Message<'o, O> where O: MessageOwner + 'o {}
@@ -33,26 +53,50 @@ impl<'o, O> FilenamesOwner for Message<'o, O> where O: MessageOwner + 'o {}
 impl<'o, O> TagsOwner for Message<'o, O> where O: MessageOwner + 'o {}
 
 
-// impl<'o, O> PartialEq for Message<'o, O>
-// where
-//     O: MessageOwner + 'o
-// {
-//     fn eq(self: &Self, other: &Message<'o, O>) -> bool{
-//         self.id() == other.id()
-//     }
-// }
+/// Messages are identified by their message-id, regardless of the query or
+/// database handle they were obtained from.
+impl<'o, 'p, O, P> PartialEq<Message<'p, P>> for Message<'o, O>
+where
+    O: MessageOwner + 'o,
+    P: MessageOwner + 'p,
+{
+    fn eq(&self, other: &Message<'p, P>) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl<'o, O> Eq for Message<'o, O> where O: MessageOwner + 'o {}
+
+impl<'o, O> fmt::Display for Message<'o, O>
+where
+    O: MessageOwner + 'o
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+impl<'o, O> Hash for Message<'o, O>
+where
+    O: MessageOwner + 'o
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
 
 impl<'o, O> Message<'o, O>
 where
     O: MessageOwner + 'o,
 {
-    pub(crate) fn from_ptr<P>(ptr: *mut ffi::notmuch_message_t, owner: P) -> Message<'o, O>
+    pub(crate) fn from_ptr<P>(ptr: *mut ffi::notmuch_message_t, owner: P, database: Database) -> Message<'o, O>
     where
         P: Into<ScopedPhantomcow<'o, O>>,
     {
         Message {
             ptr,
             marker: RefCell::new(owner.into()),
+            database,
         }
     }
 
@@ -61,45 +105,107 @@ where
         mid.to_string_lossy()
     }
 
+    /// Detach the message from the query or thread it was obtained from by
+    /// looking it up again in its database.
+    ///
+    /// Returns `Ok(None)` if the message has since been removed.
+    pub fn into_owned(self) -> Result<Option<OwnedMessage>> {
+        <Database as DatabaseExt>::find_message(self.database.clone(), &self.id())
+    }
+
     pub fn thread_id(self: &Self) -> Cow<'_, str> {
         let tid = unsafe { ffi::notmuch_message_get_thread_id(self.ptr) };
         tid.to_string_lossy()
     }
 
+    /// Get the replies to the message. Only messages obtained through a
+    /// `Thread` know their replies; for any other message, and for messages
+    /// without replies, the result is empty.
     pub fn replies(self: &Self) -> Messages<'o, O> {
         Messages::<'o, O>::from_ptr(
             unsafe { ffi::notmuch_message_get_replies(self.ptr) },
             // will never panic since the borrow is released immediately
-            ScopedPhantomcow::<'o, O>::share(&mut *(self.marker.borrow_mut()))
+            ScopedPhantomcow::<'o, O>::share(&mut *(self.marker.borrow_mut())),
+            self.database.clone(),
         )
     }
 
+    /// Get the number of files associated with the message.
+    ///
+    /// Only available from notmuch 0.25 onwards, hence the feature gate.
     #[cfg(feature = "v0_26")]
-    pub fn count_files(self: &Self) -> i32 {
-        unsafe { ffi::notmuch_message_count_files(self.ptr) }
+    pub fn count_files(self: &Self) -> Result<u32> {
+        let count = unsafe { ffi::notmuch_message_count_files(self.ptr) };
+        // notmuch signals a Xapian exception with -1.
+        if count < 0 {
+            Err(Error::NotmuchError(Status::XapianException, None))
+        } else {
+            Ok(count as u32)
+        }
     }
 
     pub fn filenames(self: &Self) -> Filenames<Self> {
         <Self as MessageExt<'o, O>>::filenames(self)
     }
 
+    /// Get a filename for the message. Filenames are raw bytes and need
+    /// not be valid UTF-8. See `filenames` for messages with several files.
+    ///
+    /// Which of several files is returned is up to notmuch, and the file
+    /// may no longer exist; see `existing_filenames` for a stable choice.
     pub fn filename(self: &Self) -> PathBuf {
-        PathBuf::from(
-            unsafe { ffi::notmuch_message_get_filename(self.ptr) }
-                .to_str()
-                .unwrap(),
-        )
+        unsafe { ffi::notmuch_message_get_filename(self.ptr) }
+            .to_path()
+            .to_path_buf()
+    }
+
+    /// Get the filenames of the message that still exist on disk, in
+    /// sorted order. Files removed since the last `notmuch new` are left
+    /// out.
+    pub fn existing_filenames(self: &Self) -> Vec<PathBuf> {
+        let mut names = self.filenames().into_existing();
+        names.sort();
+        names
     }
 
     pub fn date(&self) -> i64 {
         unsafe { ffi::notmuch_message_get_date(self.ptr) as i64 }
     }
 
+    /// Get the date of the message as a UTC `DateTime`.
+    ///
+    /// Negative timestamps (dates before 1970) convert as such. A value
+    /// outside of the range `chrono` can represent maps to the Unix epoch.
+    #[cfg(feature = "chrono")]
+    pub fn datetime(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.date(), 0).unwrap_or_default()
+    }
+
+    /// Get the date of the message as a UTC `OffsetDateTime`.
+    ///
+    /// notmuch reports a date of `0` for messages without a parseable
+    /// `Date:` header, which maps to the Unix epoch rather than an error.
+    #[cfg(feature = "time")]
+    pub fn date_offset(&self) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(self.date()).unwrap_or(OffsetDateTime::UNIX_EPOCH)
+    }
+
+    /// Get the value of the specified header from the message.
+    ///
+    /// Returns `Ok(None)` if the message has no such header. notmuch
+    /// reports an absent header and a header with an empty value
+    /// identically, so the latter is also `Ok(None)`. An error is only
+    /// returned when notmuch fails to look the header up, which usually
+    /// means the message file could not be read.
+    ///
+    /// Header names are case-insensitive, as in RFC 5322: notmuch compares
+    /// them ignoring case, so `header("message-id")` and
+    /// `header("Message-ID")` return the same value.
     pub fn header(&self, name: &str) -> Result<Option<Cow<'_, str>>> {
-        let name = CString::new(name).unwrap();
+        let name = CString::new(name)?;
         let ret = unsafe { ffi::notmuch_message_get_header(self.ptr, name.as_ptr()) };
         if ret.is_null() {
-            Err(Error::UnspecifiedError)
+            Err(Error::NotmuchError(Status::FileError, None))
         } else {
             let ret_str = ret.to_string_lossy();
             if ret_str.is_empty() {
@@ -110,36 +216,193 @@ where
         }
     }
 
+    /// Like `header`, but copies the value so it does not borrow from the
+    /// message.
+    pub fn header_owned(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.header(name)?.map(Cow::into_owned))
+    }
+
+    /// Like `header`, but returns the raw bytes of the value instead of
+    /// replacing invalid UTF-8.
+    pub fn header_bytes(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let name = CString::new(name)?;
+        let ret = unsafe { ffi::notmuch_message_get_header(self.ptr, name.as_ptr()) };
+        if ret.is_null() {
+            Err(Error::NotmuchError(Status::FileError, None))
+        } else {
+            let ret_bytes = unsafe { CStr::from_ptr(ret) }.to_bytes();
+            if ret_bytes.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(ret_bytes.to_vec()))
+            }
+        }
+    }
+
+    /// Read all headers from the message file, in the order they appear.
+    ///
+    /// Folded header lines are unfolded. Unlike `header`, this is not
+    /// limited to the headers notmuch knows about, but it does read the
+    /// file on every call.
+    pub fn headers(&self) -> Result<Vec<(String, String)>> {
+        let data = fs::read(self.filename())?;
+        Ok(parse_headers(&data))
+    }
+
+    /// Read every occurrence of the header `name` from the message file,
+    /// in order. Header names are compared case-insensitively.
+    pub fn header_values(&self, name: &str) -> Result<Vec<String>> {
+        Ok(self.headers()?
+            .into_iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+            .collect())
+    }
+
+    /// Get the value of the specified flag for the message.
+    #[cfg(feature = "v0_34")]
+    pub fn get_flag(&self, flag: MessageFlag) -> Result<bool> {
+        let mut is_set = ffi::FALSE;
+        unsafe {
+            ffi::notmuch_message_get_flag_st(self.ptr, flag.into(), &mut is_set)
+        }.as_result()?;
+
+        Ok(is_set != ffi::FALSE)
+    }
+
+    /// Get the value of the specified flag for the message.
+    #[cfg(not(feature = "v0_34"))]
+    pub fn get_flag(&self, flag: MessageFlag) -> Result<bool> {
+        Ok(unsafe { ffi::notmuch_message_get_flag(self.ptr, flag.into()) } != ffi::FALSE)
+    }
+
+    /// Whether the message matched the query it was found through, as
+    /// opposed to being included only as part of a matching thread.
+    pub fn matched(&self) -> Result<bool> {
+        self.get_flag(MessageFlag::Match)
+    }
+
+    /// Set the value of the specified flag for the message.
+    pub fn set_flag(&self, flag: MessageFlag, value: bool) {
+        unsafe { ffi::notmuch_message_set_flag(self.ptr, flag.into(), value as ffi::notmuch_bool_t) }
+    }
+
     pub fn tags(&self) -> Tags<Self> {
         <Self as MessageExt<'o, O>>::tags(self)
     }
 
+    /// Get a sorted snapshot of the message's current tags.
+    pub fn tags_vec(&self) -> Vec<String> {
+        self.tags().into_sorted_vec()
+    }
+
     pub fn add_tag(self: &Self, tag: &str) -> Result<()> {
-        let tag = CString::new(tag).unwrap();
+        self.database.check_writable()?;
+        let tag = CString::new(tag)?;
         unsafe { ffi::notmuch_message_add_tag(self.ptr, tag.as_ptr()) }.as_result()
     }
 
     pub fn remove_tag(self: &Self, tag: &str) -> Result<()> {
-        let tag = CString::new(tag).unwrap();
+        self.database.check_writable()?;
+        let tag = CString::new(tag)?;
         unsafe { ffi::notmuch_message_remove_tag(self.ptr, tag.as_ptr()) }.as_result()
     }
 
+    /// Remove `tag` if the message has it and add it otherwise, returning
+    /// whether the message has the tag afterwards. The check and the change
+    /// happen while the message is frozen, so they are committed at once.
+    pub fn toggle_tag(&self, tag: &str) -> Result<bool> {
+        self.freeze()?;
+        let res = if self.tags().contains(tag) {
+            self.remove_tag(tag).map(|_| false)
+        } else {
+            self.add_tag(tag).map(|_| true)
+        };
+        self.thaw()?;
+        res
+    }
+
     pub fn remove_all_tags(self: &Self) -> Result<()> {
+        self.database.check_writable()?;
         unsafe { ffi::notmuch_message_remove_all_tags(self.ptr) }.as_result()
     }
 
+    /// Start a batch of tag changes, applied together by `TagBatch::apply`.
+    pub fn batch_tags(&self) -> TagBatch<'_, 'o, O> {
+        TagBatch::new(self)
+    }
+
+    /// Replace all tags of the message with `tags`, committing the change
+    /// at once.
+    pub fn set_tags<I, S>(&self, tags: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        tags.into_iter()
+            .fold(self.batch_tags().remove_all_tags(), |batch, tag| batch.add_tag(tag.as_ref()))
+            .apply()
+    }
+
     pub fn tags_to_maildir_flags(self: &Self) -> Result<()> {
+        self.database.check_writable()?;
         unsafe { ffi::notmuch_message_tags_to_maildir_flags(self.ptr) }.as_result()
     }
 
     pub fn maildir_flags_to_tags(self: &Self) -> Result<()> {
+        self.database.check_writable()?;
         unsafe { ffi::notmuch_message_maildir_flags_to_tags(self.ptr) }.as_result()
     }
 
+    /// Replace all tags of the message with those derived from its maildir
+    /// flags, committing the change at once so the message is never seen
+    /// without tags.
+    pub fn reset_tags_from_maildir(&self) -> Result<()> {
+        self.freeze()?;
+        let res = self.remove_all_tags().and_then(|_| self.maildir_flags_to_tags());
+        self.thaw()?;
+        res
+    }
+
     pub fn reindex<'d>(self: &Self, indexopts: IndexOpts<'d>) -> Result<()> {
+        self.database.check_writable()?;
         unsafe { ffi::notmuch_message_reindex(self.ptr, indexopts.ptr) }.as_result()
     }
 
+    /// Like `reindex`, using the default index options of the message's
+    /// database.
+    pub fn reindex_default(self: &Self) -> Result<()> {
+        self.reindex(self.database().default_indexopts()?)
+    }
+
+    /// Reindex the message with the default index options, then add the
+    /// tags configured in `new.tags`, as `notmuch new` does for new mail.
+    /// Tags the message already has are kept.
+    #[cfg(feature = "v0_32")]
+    pub fn reindex_and_apply_new_tags(&self) -> Result<()> {
+        self.reindex_default()?;
+        let tags = self.database().config_values("new.tags")?;
+
+        self.freeze()?;
+        let res = tags.iter().try_for_each(|tag| self.add_tag(tag));
+        self.thaw()?;
+        res
+    }
+
+    /// Get the database the message belongs to. This is a clone of the
+    /// handle the message was found through, sharing its mode and atomic
+    /// operations.
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    /// Get the thread the message belongs to, by searching the message's
+    /// database for its thread id.
+    pub fn thread(&self) -> Result<Option<Thread<'_, '_>>> {
+        let query = format!("thread:{}", self.thread_id());
+        Ok(self.database().search_threads(&query)?.next())
+    }
+
     pub fn freeze(self: &Self) -> Result<()> {
         unsafe { ffi::notmuch_message_freeze(self.ptr) }.as_result()
     }
@@ -148,15 +411,16 @@ where
         unsafe { ffi::notmuch_message_thaw(self.ptr) }.as_result()
     }
 
-    pub fn properties<'m>(&'m self, key: &str, exact: bool) -> MessageProperties<'m, 'o, O> {
+    pub fn properties<'m>(&'m self, key: &str, exact: bool) -> Result<MessageProperties<'m, 'o, O>> {
         <Self as MessageExt<'o, O>>::properties(self, key, exact)
     }
 
     pub fn remove_all_properties(&self, key: Option<&str>) -> Result<()>
     {
+        self.database.check_writable()?;
         match key {
             Some(k) => {
-                let key_str = CString::new(k).unwrap();
+                let key_str = CString::new(k)?;
                 unsafe {
                     ffi::notmuch_message_remove_all_properties(self.ptr, key_str.as_ptr())
                 }.as_result()
@@ -170,28 +434,19 @@ where
         }
     }
 
-    pub fn remove_all_properties_with_prefix(&self, prefix: Option<&str>) -> Result<()>
+    pub fn remove_all_properties_with_prefix(&self, prefix: &str) -> Result<()>
     {
-        match prefix {
-            Some(k) => {
-                let key_str = CString::new(k).unwrap();
-                unsafe {
-                    ffi::notmuch_message_remove_all_properties_with_prefix(self.ptr, key_str.as_ptr())
-                }.as_result()
-            },
-            None => {
-                let p = ptr::null();
-                unsafe {
-                    ffi::notmuch_message_remove_all_properties_with_prefix(self.ptr, p)
-                }.as_result()
-            }
-        }
+        self.database.check_writable()?;
+        let prefix_str = CString::new(prefix)?;
+        unsafe {
+            ffi::notmuch_message_remove_all_properties_with_prefix(self.ptr, prefix_str.as_ptr())
+        }.as_result()
     }
 
 
     pub fn count_properties(&self, key: &str) -> Result<u32>
     {
-        let key_str = CString::new(key).unwrap();
+        let key_str = CString::new(key)?;
         let mut cnt = 0;
         unsafe {
             ffi::notmuch_message_count_properties(self.ptr, key_str.as_ptr(), &mut cnt)
@@ -202,7 +457,7 @@ where
 
     pub fn property(&self, key: &str) -> Result<Cow<'_, str>>
     {
-        let key_str = CString::new(key).unwrap();
+        let key_str = CString::new(key)?;
         let mut prop = ptr::null();
         unsafe {
             ffi::notmuch_message_get_property(self.ptr, key_str.as_ptr(), &mut prop)
@@ -216,10 +471,24 @@ where
         }
     }
 
+    /// Get the first value of the property named exactly `key`, or
+    /// `Ok(None)` if the message has no such property.
+    pub fn get_first(&self, key: &str) -> Result<Option<String>>
+    {
+        Ok(self.properties(key, true)?.next().map(|(_, value)| value))
+    }
+
+    /// Get all values of the property named exactly `key`.
+    pub fn get_all(&self, key: &str) -> Result<Vec<String>>
+    {
+        Ok(self.properties(key, true)?.collect_values())
+    }
+
     pub fn add_property(&self, key: &str, value: &str) -> Result<()>
     {
-        let key_str = CString::new(key).unwrap();
-        let value_str = CString::new(value).unwrap();
+        self.database.check_writable()?;
+        let key_str = CString::new(key)?;
+        let value_str = CString::new(value)?;
         unsafe {
             ffi::notmuch_message_add_property(self.ptr, key_str.as_ptr(), value_str.as_ptr())
         }.as_result()
@@ -227,8 +496,9 @@ where
 
     pub fn remove_property(&self, key: &str, value: &str) -> Result<()>
     {
-        let key_str = CString::new(key).unwrap();
-        let value_str = CString::new(value).unwrap();
+        self.database.check_writable()?;
+        let key_str = CString::new(key)?;
+        let value_str = CString::new(value)?;
         unsafe {
             ffi::notmuch_message_remove_property(self.ptr, key_str.as_ptr(), value_str.as_ptr())
         }.as_result()
@@ -272,32 +542,90 @@ where
         )
     }
 
-    fn properties<'m, M>(message: M, key: &str, exact: bool) -> MessageProperties<'m, 'o, O>
+    fn properties<'m, M>(message: M, key: &str, exact: bool) -> Result<MessageProperties<'m, 'o, O>>
     where
         M: Into<ScopedSupercow<'m, Message<'o, O>>>,
     {
         let messageref = message.into();
-        let key_str = CString::new(key).unwrap();
+        let key_str = CString::new(key)?;
 
         let props = unsafe {
             ffi::notmuch_message_get_properties(messageref.ptr, key_str.as_ptr(), exact as i32)
         };
 
-        MessageProperties::from_ptr(props, Supercow::phantom(messageref))
+        Ok(MessageProperties::from_ptr(props, Supercow::phantom(messageref)))
     }
 }
 
 impl<'o, O> MessageExt<'o, O> for Message<'o, O> where O: MessageOwner + 'o {}
 
+fn parse_headers(data: &[u8]) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = vec![];
+
+    for line in data.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            break;
+        }
+
+        let line = String::from_utf8_lossy(line);
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(last) = headers.last_mut() {
+                last.1.push_str(&line);
+            }
+        } else if let Some(colon) = line.find(':') {
+            let (name, value) = line.split_at(colon);
+            headers.push((name.trim_end().to_string(), value[1..].trim_start().to_string()));
+        }
+    }
+
+    headers
+}
+
+/// Serializes a snapshot of the message: its ids, timestamp, filenames
+/// and tags are read from notmuch up front.
+#[cfg(feature = "serde")]
+impl<'o, O> Serialize for Message<'o, O>
+where
+    O: MessageOwner + 'o,
+{
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let filenames: Vec<String> = self
+            .filenames()
+            .map(|f| f.to_string_lossy().into_owned())
+            .collect();
+        let tags: Vec<String> = self.tags().collect();
+
+        let mut state = serializer.serialize_struct("Message", 5)?;
+        state.serialize_field("id", &self.id())?;
+        state.serialize_field("thread_id", &self.thread_id())?;
+        state.serialize_field("timestamp", &self.date())?;
+        state.serialize_field("filenames", &filenames)?;
+        state.serialize_field("tags", &tags)?;
+        state.end()
+    }
+}
+
 unsafe impl<'o, O> Send for Message<'o, O> where O: MessageOwner + 'o {}
 unsafe impl<'o, O> Sync for Message<'o, O> where O: MessageOwner + 'o {}
 
 
+/// Keeps a message frozen while it is alive, so that changes made through
+/// it are committed together when it is thawed or dropped.
+///
+/// Call `thaw` to commit deliberately and learn whether thawing failed,
+/// e.g. with `Status::UnbalancedFreezeThaw` when the message was thawed
+/// behind the guard's back. Dropping the guard, including while unwinding
+/// from a panic, still thaws the message but has no way to report errors.
 pub struct FrozenMessage<'m ,'o, O>
 where
     O: MessageOwner + 'o
 {
-    message: ScopedSupercow<'m, Message<'o, O>>
+    message: ScopedSupercow<'m, Message<'o, O>>,
+    thawed: bool,
 }
 
 
@@ -312,9 +640,28 @@ where
         let msg = message.into();
         msg.freeze()?;
         Ok(FrozenMessage{
-            message: msg
+            message: msg,
+            thawed: false,
         })
     }
+
+    /// Thaw the message, reporting any error that dropping the guard
+    /// would otherwise discard.
+    pub fn thaw(mut self) -> Result<()> {
+        self.thawed = true;
+        self.message.thaw()
+    }
+}
+
+impl<'m, 'o, O> Deref for FrozenMessage<'m, 'o, O>
+where
+    O: MessageOwner + 'o
+{
+    type Target = Message<'o, O>;
+
+    fn deref(&self) -> &Message<'o, O> {
+        &self.message
+    }
 }
 
 impl<'m, 'o, O> Drop for FrozenMessage<'m, 'o, O>
@@ -322,8 +669,146 @@ where
     O: MessageOwner + 'o
 {
     fn drop(&mut self) {
-        let _ = self.message.thaw();
+        if !self.thawed {
+            let _ = self.message.thaw();
+        }
+    }
+}
+
+
+/// A message that is destroyed as soon as it is dropped, rather than
+/// together with the query it came from. See `Query::search_messages_lazy`.
+pub struct TransientMessage<'o, O>
+where
+    O: MessageOwner + 'o
+{
+    message: Message<'o, O>,
+}
+
+impl<'o, O> TransientMessage<'o, O>
+where
+    O: MessageOwner + 'o
+{
+    /// The caller must guarantee that no other `Message` refers to the same
+    /// underlying notmuch message.
+    pub(crate) unsafe fn new(message: Message<'o, O>) -> Self {
+        TransientMessage {
+            message,
+        }
+    }
+}
+
+impl<'o, O> Deref for TransientMessage<'o, O>
+where
+    O: MessageOwner + 'o
+{
+    type Target = Message<'o, O>;
+
+    fn deref(&self) -> &Message<'o, O> {
+        &self.message
+    }
+}
+
+impl<'o, O> Drop for TransientMessage<'o, O>
+where
+    O: MessageOwner + 'o
+{
+    fn drop(&mut self) {
+        unsafe { ffi::notmuch_message_destroy(self.message.ptr) };
     }
 }
 
 
+/// A set of tag changes to a message, applied while the message is frozen
+/// so that they are committed at once.
+///
+/// If any change fails, the message's original tags are restored before
+/// thawing, leaving it as it was. Dropping a batch that has not been
+/// applied applies it, discarding any error.
+///
+/// The methods are named after their `Message` counterparts; a bare `add`
+/// taking and returning `self` would pass for `std::ops::Add::add`.
+#[derive(Debug)]
+pub struct TagBatch<'m, 'o, O>
+where
+    O: MessageOwner + 'o
+{
+    message: &'m Message<'o, O>,
+    changes: Vec<TagChange>,
+    applied: bool,
+}
+
+#[derive(Debug)]
+enum TagChange {
+    Add(String),
+    Remove(String),
+    RemoveAll,
+}
+
+impl<'m, 'o, O> TagBatch<'m, 'o, O>
+where
+    O: MessageOwner + 'o
+{
+    fn new(message: &'m Message<'o, O>) -> Self {
+        TagBatch {
+            message,
+            changes: vec![],
+            applied: false,
+        }
+    }
+
+    pub fn add_tag(mut self, tag: &str) -> Self {
+        self.changes.push(TagChange::Add(tag.to_string()));
+        self
+    }
+
+    pub fn remove_tag(mut self, tag: &str) -> Self {
+        self.changes.push(TagChange::Remove(tag.to_string()));
+        self
+    }
+
+    pub fn remove_all_tags(mut self) -> Self {
+        self.changes.push(TagChange::RemoveAll);
+        self
+    }
+
+    pub fn apply(mut self) -> Result<()> {
+        self._apply()
+    }
+
+    fn _apply(&mut self) -> Result<()> {
+        self.applied = true;
+
+        let original: Vec<String> = self.message.tags().collect();
+
+        self.message.freeze()?;
+        let res = self.changes.iter().try_for_each(|change| match change {
+            TagChange::Add(tag) => self.message.add_tag(tag),
+            TagChange::Remove(tag) => self.message.remove_tag(tag),
+            TagChange::RemoveAll => self.message.remove_all_tags(),
+        });
+
+        if res.is_err() {
+            // Restoring tags that were valid before cannot hit the same
+            // failure, so there is nothing sensible to do with an error here.
+            let _ = self.message.remove_all_tags();
+            for tag in &original {
+                let _ = self.message.add_tag(tag);
+            }
+        }
+
+        self.message.thaw()?;
+        res
+    }
+}
+
+impl<'m, 'o, O> Drop for TagBatch<'m, 'o, O>
+where
+    O: MessageOwner + 'o
+{
+    fn drop(&mut self) {
+        if !self.applied {
+            let _ = self._apply();
+        }
+    }
+}
@@ -38,6 +38,11 @@ where
             marker: owner.into(),
         }
     }
+
+    /// Consume the properties, returning only their values.
+    pub fn collect_values(self) -> Vec<String> {
+        self.map(|(_, value)| value).collect()
+    }
 }
 
 
@@ -1,10 +1,24 @@
+use error::{Error, Result};
 use ffi;
+use ffi::Status;
 use utils::ScopedPhantomcow;
+use Database;
 use MessageOwner;
 use Message;
 use Tags;
 use TagsOwner;
 
+/// A forward-only iterator over a set of messages, such as the results of
+/// a query, the messages of a thread or the replies to a message.
+///
+/// The underlying `notmuch_messages_t` can not be rewound, so a set can
+/// only be walked once, e.g. by a `for` loop. Each yielded `Message` is
+/// bound to the owner `O` of the set rather than to the iterator, and
+/// remains valid after the iterator has moved past it.
+///
+/// Consequently a set does not know its length: `Iterator::count` walks
+/// and consumes it. Use `Query::count_messages` to count the results of a
+/// query without materializing them.
 #[derive(Debug)]
 pub struct Messages<'o, O>
 where
@@ -12,28 +26,27 @@ where
 {
     pub(crate) ptr: *mut ffi::notmuch_messages_t,
     marker: ScopedPhantomcow<'o, O>,
+    database: Database,
 }
 
-// impl<'o, O> Drop for Messages<'o, O>
-// where
-//     O: MessageOwner + 'o,
-// {
-//     fn drop(self: &mut Self) {
-//         unsafe { ffi::notmuch_messages_destroy(self.ptr) };
-//     }
-// }
+// Messages deliberately has no Drop calling notmuch_messages_destroy.
+// notmuch allocates every message it yields as a child of the set, so
+// destroying the set would free messages that are still alive on the Rust
+// side, which are bound to the owner `O` instead. The set is freed along
+// with its owner, e.g. when the query or thread is destroyed.
 
 impl<'o, O> Messages<'o, O>
 where
     O: MessageOwner + 'o,
 {
-    pub(crate) fn from_ptr<P>(ptr: *mut ffi::notmuch_messages_t, owner: P) -> Messages<'o, O>
+    pub(crate) fn from_ptr<P>(ptr: *mut ffi::notmuch_messages_t, owner: P, database: Database) -> Messages<'o, O>
     where
         P: Into<ScopedPhantomcow<'o, O>>,
     {
         Messages {
             ptr,
             marker: owner.into(),
+            database,
         }
     }
 }
@@ -56,13 +69,20 @@ where
      * way how you can iterate over the list again is to recreate the
      * message list.
      *
-     * The function returns NULL on error.
+     * The returned tags borrow the message list.
      */
-    pub fn collect_tags<'m>(self: &'o Self) -> Tags<'m, Self> {
-        Tags::from_ptr(
-            unsafe { ffi::notmuch_messages_collect_tags(self.ptr) },
-            self,
-        )
+    pub fn collect_tags(&self) -> Result<Tags<'_, Self>> {
+        if self.ptr.is_null() {
+            return Err(Error::NotmuchError(Status::NullPointer, None));
+        }
+
+        let tags = unsafe { ffi::notmuch_messages_collect_tags(self.ptr) };
+
+        if tags.is_null() {
+            Err(Error::NotmuchError(Status::OutOfMemory, None))
+        } else {
+            Ok(Tags::from_ptr(tags, self))
+        }
     }
 }
 
@@ -73,6 +93,11 @@ where
     type Item = Message<'o, O>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        // notmuch hands out a NULL set for messages without replies.
+        if self.ptr.is_null() {
+            return None;
+        }
+
         let valid = unsafe { ffi::notmuch_messages_valid(self.ptr) };
 
         if valid == 0 {
@@ -85,7 +110,7 @@ where
             thrd
         };
 
-        Some(Message::from_ptr(cthrd, ScopedPhantomcow::<'o, O>::share(&mut self.marker)))
+        Some(Message::from_ptr(cthrd, ScopedPhantomcow::<'o, O>::share(&mut self.marker), self.database.clone()))
     }
 }
 
@@ -2,13 +2,14 @@ use std::ops::Drop;
 use std::ptr;
 use std::ffi::{CStr, CString};
 
-use supercow::{Phantomcow, Supercow};
+use supercow::Supercow;
 
 use error::Result;
 use ffi;
-use ffi::{Sort, Exclude};
+use ffi::{Sort, Exclude, Status};
 use Database;
 use Messages;
+use TransientMessage;
 use MessageOwner;
 use Threads;
 use DatabaseExt;
@@ -17,7 +18,7 @@ use utils::ScopedSupercow;
 #[derive(Debug)]
 pub struct Query<'d> {
     pub(crate) ptr: *mut ffi::notmuch_query_t,
-    marker: Phantomcow<'d, Database>,
+    database: Supercow<'d, Database>,
 }
 
 impl<'d> Drop for Query<'d> {
@@ -31,11 +32,11 @@ impl<'d> MessageOwner for Query<'d> {}
 impl<'d> Query<'d> {
     pub(crate) fn from_ptr<O>(ptr: *mut ffi::notmuch_query_t, owner: O) -> Query<'d>
     where
-        O: Into<Phantomcow<'d, Database>>,
+        O: Into<Supercow<'d, Database>>,
     {
         Query {
             ptr,
-            marker: owner.into(),
+            database: owner.into(),
         }
     }
 
@@ -46,11 +47,35 @@ impl<'d> Query<'d> {
         <Database as DatabaseExt>::create_query(db, query_string)
     }
 
+    /// Return the query string this query was created with.
     pub fn query_string(self: &Self) -> String {
         let qstring = unsafe {
             CStr::from_ptr(ffi::notmuch_query_get_query_string(self.ptr))
         };
-        qstring.to_str().unwrap().to_string()
+        qstring.to_string_lossy().into_owned()
+    }
+
+    /// Return the database this query was created for.
+    pub fn database(self: &Self) -> &Database {
+        &self.database
+    }
+
+    /// Create a query matching the messages of this one that also match
+    /// `extra`, on the same database and with the same sort order.
+    ///
+    /// Tag exclusions and `set_omit_excluded` are not carried over.
+    pub fn refine<'s>(self: &'s Self, extra: &str) -> Result<Query<'s>> {
+        let base = self.query_string();
+        // notmuch reads both a blank query and a lone `*` as match-all.
+        let refined = if base.trim().is_empty() || base.trim() == "*" {
+            format!("({})", extra)
+        } else {
+            format!("({}) AND ({})", base, extra)
+        };
+
+        let query = <Database as DatabaseExt>::create_query(&*self.database, &refined)?;
+        query.set_sort(self.sort());
+        Ok(query)
     }
 
     /// Specify the sorting desired for this query.
@@ -64,14 +89,34 @@ impl<'d> Query<'d> {
         unsafe { ffi::notmuch_query_get_sort(self.ptr) }.into()
     }
 
-    /// Filter messages according to the query and return
+    /// Filter messages according to the query and return them in the
+    /// order given by `sort`. Combine with `Iterator::enumerate` to number
+    /// the results as they are walked.
     pub fn search_messages<'q>(self: &'d Self) -> Result<Messages<'q, Self>> {
         <Query as QueryExt>::search_messages(self)
     }
 
+    /// Like `search_messages`, but each message is destroyed as soon as it
+    /// is dropped instead of together with the query. This keeps memory use
+    /// flat when walking very large result sets.
+    pub fn search_messages_lazy(&self) -> Result<impl Iterator<Item = TransientMessage<'_, Self>>> {
+        let messages = <Query as QueryExt>::search_messages(self)?;
+        // Query results are fresh objects, each handed out only once.
+        Ok(messages.map(|message| unsafe { TransientMessage::new(message) }))
+    }
+
+    /// Return the number of messages matching the query, without
+    /// materializing them.
     pub fn count_messages(self: &Self) -> Result<u32> {
         let mut cnt = 0;
-        unsafe { ffi::notmuch_query_count_messages(self.ptr, &mut cnt) }.as_result()?;
+
+        self.database.status_result(|| {
+            #[cfg(feature = "v0_26")]
+            let status = unsafe { ffi::notmuch_query_count_messages(self.ptr, &mut cnt) };
+            #[cfg(not(feature = "v0_26"))]
+            let status = unsafe { ffi::notmuch_query_count_messages_st(self.ptr, &mut cnt) };
+            status
+        })?;
 
         Ok(cnt)
     }
@@ -80,24 +125,119 @@ impl<'d> Query<'d> {
         <Query<'d> as QueryExt>::search_threads(self)
     }
 
+    /// Return the number of threads matching the query. This is a
+    /// significantly heavier operation than `count_messages`.
     pub fn count_threads(self: &Self) -> Result<u32> {
         let mut cnt = 0;
-        unsafe { ffi::notmuch_query_count_threads(self.ptr, &mut cnt) }.as_result()?;
+
+        self.database.status_result(|| {
+            #[cfg(feature = "v0_26")]
+            let status = unsafe { ffi::notmuch_query_count_threads(self.ptr, &mut cnt) };
+            #[cfg(not(feature = "v0_26"))]
+            let status = unsafe { ffi::notmuch_query_count_threads_st(self.ptr, &mut cnt) };
+            status
+        })?;
 
         Ok(cnt)
     }
 
+    /// Add a tag that will be excluded from the query results by default.
     pub fn add_tag_exclude(self: &Self, tag: &str) -> Result<()>
     {
-        let tag_str = CString::new(tag).unwrap();
+        let tag_str = CString::new(tag)?;
         unsafe { ffi::notmuch_query_add_tag_exclude(self.ptr, tag_str.as_ptr()) }.as_result()
     }
 
+    /// Exclude the tags listed in the `search.exclude_tags` configuration
+    /// key, as the notmuch CLI does for every search. Tags the query
+    /// explicitly searches for are left alone.
+    pub fn apply_config_excludes(self: &Self) -> Result<()> {
+        #[cfg(feature = "v0_32")]
+        let tags = self.database.config_values("search.exclude_tags")?;
+        #[cfg(not(feature = "v0_32"))]
+        let tags: Vec<String> = self.database.config("search.exclude_tags")?
+            .map(|value| value.split(';').filter(|t| !t.is_empty()).map(|t| t.to_string()).collect())
+            .unwrap_or_default();
+
+        for tag in tags {
+            match self.add_tag_exclude(&tag) {
+                Err(ref e) if e.status() == Some(Status::Ignored) => (),
+                other => other?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Specify whether to omit excluded results or simply flag them. With
+    /// `Exclude::Flag`, excluded messages are returned with
+    /// `MessageFlag::Excluded` set.
     pub fn set_omit_excluded(self: &Self, omit_excluded: Exclude) {
         unsafe { ffi::notmuch_query_set_omit_excluded(self.ptr, omit_excluded.into()) }
     }
 }
 
+/// Collects query configuration and applies it when the query is built.
+///
+/// Obtained through `Database::query_builder`.
+#[derive(Debug)]
+pub struct QueryBuilder<'d> {
+    database: Supercow<'d, Database>,
+    query_string: String,
+    sort: Option<Sort>,
+    omit_excluded: Option<Exclude>,
+    exclude_tags: Vec<String>,
+}
+
+impl<'d> QueryBuilder<'d> {
+    pub fn new<D>(db: D, query_string: &str) -> Self
+    where
+        D: Into<Supercow<'d, Database>>,
+    {
+        QueryBuilder {
+            database: db.into(),
+            query_string: query_string.to_string(),
+            sort: None,
+            omit_excluded: None,
+            exclude_tags: vec![],
+        }
+    }
+
+    /// See `Query::set_sort`.
+    pub fn sort(mut self, sort: Sort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// See `Query::set_omit_excluded`.
+    pub fn omit_excluded(mut self, omit_excluded: Exclude) -> Self {
+        self.omit_excluded = Some(omit_excluded);
+        self
+    }
+
+    /// See `Query::add_tag_exclude`. May be called several times.
+    pub fn exclude_tag(mut self, tag: &str) -> Self {
+        self.exclude_tags.push(tag.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<Query<'d>> {
+        let query = <Database as DatabaseExt>::create_query(self.database, &self.query_string)?;
+
+        if let Some(sort) = self.sort {
+            query.set_sort(sort);
+        }
+        if let Some(omit_excluded) = self.omit_excluded {
+            query.set_omit_excluded(omit_excluded);
+        }
+        for tag in &self.exclude_tags {
+            query.add_tag_exclude(tag)?;
+        }
+
+        Ok(query)
+    }
+}
+
 pub trait QueryExt<'d> {
     fn search_threads<'q, Q>(query: Q) -> Result<Threads<'d, 'q>>
     where
@@ -106,10 +246,12 @@ pub trait QueryExt<'d> {
         let queryref = query.into();
 
         let mut thrds = ptr::null_mut();
-        unsafe { ffi::notmuch_query_search_threads(queryref.ptr, &mut thrds) }
-            .as_result()?;
+        queryref.database.status_result(|| unsafe {
+            ffi::notmuch_query_search_threads(queryref.ptr, &mut thrds)
+        })?;
 
-        Ok(Threads::from_ptr(thrds, ScopedSupercow::phantom(queryref)))
+        let database = Database::clone(&queryref.database);
+        Ok(Threads::from_ptr(thrds, ScopedSupercow::phantom(queryref), database))
     }
 
     fn search_messages<'q, Q>(query: Q) -> Result<Messages<'q, Query<'d>>>
@@ -119,10 +261,12 @@ pub trait QueryExt<'d> {
         let queryref = query.into();
 
         let mut msgs = ptr::null_mut();
-        unsafe { ffi::notmuch_query_search_messages(queryref.ptr, &mut msgs) }
-            .as_result()?;
+        queryref.database.status_result(|| unsafe {
+            ffi::notmuch_query_search_messages(queryref.ptr, &mut msgs)
+        })?;
 
-        Ok(Messages::from_ptr(msgs, ScopedSupercow::phantom(queryref)))
+        let database = Database::clone(&queryref.database);
+        Ok(Messages::from_ptr(msgs, ScopedSupercow::phantom(queryref), database))
     }
 }
 
@@ -8,6 +8,11 @@ use utils::ScopedPhantomcow;
 
 pub trait TagsOwner {}
 
+/// A forward-only iterator over a set of tags, such as those of a message
+/// or thread, or all tags in the database.
+///
+/// Use `Iterator::count` to count the tags; like any other walk over the
+/// set, it consumes the iterator.
 #[derive(Debug)]
 pub struct Tags<'o, O> where
     O: TagsOwner + 'o,
@@ -47,6 +52,21 @@ where
             marker: owner.into(),
         }
     }
+
+    /// Collect the remaining tags, sorted lexicographically. notmuch makes
+    /// no guarantee about the order it yields tags in.
+    pub fn into_sorted_vec(self) -> Vec<String> {
+        let mut tags: Vec<String> = self.collect();
+        tags.sort();
+        tags
+    }
+
+    /// Whether `tag` is among the remaining tags. This consumes the
+    /// iterator, stopping at the first match; collect the tags first to
+    /// test for several.
+    pub fn contains(mut self, tag: &str) -> bool {
+        self.any(|t| t == tag)
+    }
 }
 
 impl<'o, O> Iterator for Tags<'o, O>
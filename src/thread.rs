@@ -1,13 +1,22 @@
 use std::ops::Drop;
 use std::borrow::Cow;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, Serializer, SerializeStruct};
 
 use ffi;
 use utils::{ToStr, ScopedSupercow, ScopedPhantomcow};
+use Message;
 use Messages;
 use MessageOwner;
 use Tags;
 use TagsOwner;
 use Query;
+use Database;
 
 #[derive(Debug)]
 pub struct Thread<'d, 'q>
@@ -16,6 +25,7 @@ where
 {
     pub(crate) ptr: *mut ffi::notmuch_thread_t,
     pub(crate) marker: ScopedPhantomcow<'q, Query<'d>>,
+    database: Database,
 }
 
 impl<'d, 'q> Drop for Thread<'d, 'q>
@@ -27,6 +37,27 @@ where
     }
 }
 
+impl<'d, 'q, 'e, 'r> PartialEq<Thread<'e, 'r>> for Thread<'d, 'q>
+where
+    'd: 'q,
+    'e: 'r,
+{
+    fn eq(&self, other: &Thread<'e, 'r>) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl<'d, 'q> Eq for Thread<'d, 'q> where 'd: 'q {}
+
+impl<'d, 'q> Hash for Thread<'d, 'q>
+where
+    'd: 'q
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
 impl<'d, 'q> MessageOwner for Thread<'d, 'q> where 'd: 'q {}
 impl<'d, 'q> TagsOwner for Thread<'d, 'q> where 'd: 'q {}
 
@@ -34,13 +65,14 @@ impl<'d, 'q> Thread<'d, 'q>
 where
     'd: 'q
 {
-    pub(crate) fn from_ptr<P>(ptr: *mut ffi::notmuch_thread_t, owner: P) -> Thread<'d, 'q>
+    pub(crate) fn from_ptr<P>(ptr: *mut ffi::notmuch_thread_t, owner: P, database: Database) -> Thread<'d, 'q>
     where
         P: Into<ScopedPhantomcow<'q, Query<'d>>>,
     {
         Thread {
             ptr,
             marker: owner.into(),
+            database,
         }
     }
 
@@ -49,11 +81,13 @@ where
         tid.to_str().unwrap()
     }
 
+    /// Get the total number of messages in the thread, matched or not.
     pub fn total_messages(self: &Self) -> i32 {
         unsafe { ffi::notmuch_thread_get_total_messages(self.ptr) }
     }
 
-    #[cfg(feature = "0.26")]
+    /// Get the total number of files in the thread.
+    #[cfg(feature = "v0_26")]
     pub fn total_files(self: &Self) -> i32 {
         unsafe { ffi::notmuch_thread_get_total_files(self.ptr) }
     }
@@ -62,6 +96,32 @@ where
         <Self as ThreadExt<'d, 'q>>::toplevel_messages(self)
     }
 
+    /// Walk the reply tree of the thread depth-first, pairing each message
+    /// with its depth below the top-level messages. Messages reached a
+    /// second time, e.g. through circular `References`, are skipped.
+    pub fn messages_with_depth(self: &Self) -> Vec<(usize, Message<'_, Self>)> {
+        let mut walked = vec![];
+        let mut seen = HashSet::new();
+
+        let mut stack: Vec<_> = self.toplevel_messages().map(|m| (0, m)).collect();
+        stack.reverse();
+        while let Some((depth, message)) = stack.pop() {
+            if !seen.insert(message.id().into_owned()) {
+                continue;
+            }
+
+            let mut replies: Vec<_> = message.replies().map(|m| (depth + 1, m)).collect();
+            replies.reverse();
+            stack.extend(replies);
+
+            walked.push((depth, message));
+        }
+
+        walked
+    }
+
+    /// Get the number of messages in the thread that matched the search
+    /// and were not excluded.
     pub fn matched_messages(self: &Self) -> i32 {
         unsafe { ffi::notmuch_thread_get_matched_messages(self.ptr) }
     }
@@ -72,10 +132,22 @@ where
         <Self as ThreadExt<'d, 'q>>::messages(self)
     }
 
+    /// Get the most recent message of the thread by date, if any.
+    pub fn newest_message(self: &Self) -> Option<Message<'_, Self>> {
+        self.messages().max_by_key(|m| m.date())
+    }
+
+    /// Get the earliest message of the thread by date, if any.
+    pub fn oldest_message(self: &Self) -> Option<Message<'_, Self>> {
+        self.messages().min_by_key(|m| m.date())
+    }
+
     pub fn tags(&self) -> Tags<'_, Self> {
         <Self as ThreadExt<'d, 'q>>::tags(self)
     }
 
+    /// Get the subject of the thread, taken from the first message in
+    /// query order.
     pub fn subject(self: &Self) -> Cow<'_, str> {
         let sub = unsafe { ffi::notmuch_thread_get_subject(self.ptr) };
         sub.to_string_lossy()
@@ -91,6 +163,26 @@ where
             .collect()
     }
 
+    /// Get the authors of the thread, split into those of messages that
+    /// matched the query and those of messages that did not.
+    pub fn authors_split(self: &Self) -> (Vec<String>, Vec<String>) {
+        let athrs = unsafe { ffi::notmuch_thread_get_authors(self.ptr) };
+        let athrs = athrs.to_string_lossy();
+
+        let mut groups = athrs.splitn(2, '|').map(|group| {
+            group
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect()
+        });
+
+        let matched = groups.next().unwrap_or_default();
+        let unmatched = groups.next().unwrap_or_default();
+        (matched, unmatched)
+    }
+
     /// Get the date of the oldest message in 'thread' as a time_t value.
     pub fn oldest_date(self: &Self) -> i64 {
         unsafe { ffi::notmuch_thread_get_oldest_date(self.ptr) as i64 }
@@ -100,6 +192,20 @@ where
     pub fn newest_date(self: &Self) -> i64 {
         unsafe { ffi::notmuch_thread_get_newest_date(self.ptr) as i64 }
     }
+
+    /// Get the date of the oldest message in the thread as a UTC `DateTime`.
+    /// See `Message::datetime`.
+    #[cfg(feature = "chrono")]
+    pub fn oldest_datetime(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.oldest_date(), 0).unwrap_or_default()
+    }
+
+    /// Get the date of the newest message in the thread as a UTC `DateTime`.
+    /// See `Message::datetime`.
+    #[cfg(feature = "chrono")]
+    pub fn newest_datetime(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.newest_date(), 0).unwrap_or_default()
+    }
 }
 
 pub trait ThreadExt<'d, 'q>
@@ -122,9 +228,11 @@ where
         S: Into<ScopedSupercow<'s, Thread<'d, 'q>>>,
     {
         let threadref = thread.into();
+        let database = threadref.database.clone();
         Messages::from_ptr(
             unsafe { ffi::notmuch_thread_get_toplevel_messages(threadref.ptr) },
             ScopedSupercow::phantom(threadref),
+            database,
         )
     }
 
@@ -135,14 +243,44 @@ where
         S: Into<ScopedSupercow<'s, Thread<'d, 'q>>>,
     {
         let threadref = thread.into();
+        let database = threadref.database.clone();
         Messages::from_ptr(
             unsafe { ffi::notmuch_thread_get_messages(threadref.ptr) },
             ScopedSupercow::phantom(threadref),
+            database,
         )
     }
 }
 
 impl<'d, 'q> ThreadExt<'d, 'q> for Thread<'d, 'q> where 'd: 'q {}
 
+/// Serializes a summary of the thread, in the spirit of
+/// `notmuch search --format=json`.
+#[cfg(feature = "serde")]
+impl<'d, 'q> Serialize for Thread<'d, 'q>
+where
+    'd: 'q
+{
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (matched_authors, unmatched_authors) = self.authors_split();
+        let tags: Vec<String> = self.tags().collect();
+
+        let mut state = serializer.serialize_struct("Thread", 9)?;
+        state.serialize_field("thread_id", self.id())?;
+        state.serialize_field("subject", &self.subject())?;
+        state.serialize_field("matched", &self.matched_messages())?;
+        state.serialize_field("total", &self.total_messages())?;
+        state.serialize_field("matched_authors", &matched_authors)?;
+        state.serialize_field("unmatched_authors", &unmatched_authors)?;
+        state.serialize_field("oldest_timestamp", &self.oldest_date())?;
+        state.serialize_field("newest_timestamp", &self.newest_date())?;
+        state.serialize_field("tags", &tags)?;
+        state.end()
+    }
+}
+
 unsafe impl<'d, 'q> Send for Thread<'d, 'q> where 'd: 'q {}
 unsafe impl<'d, 'q> Sync for Thread<'d, 'q> where 'd: 'q {}
@@ -3,9 +3,14 @@ use std::ops::Drop;
 use ffi;
 use Thread;
 use Query;
+use Database;
 use utils::ScopedPhantomcow;
 
 
+/// A forward-only iterator over the threads matching a query.
+///
+/// Being an `Iterator`, it can be used directly in a `for` loop, which
+/// consumes it; the set can only be walked once.
 #[derive(Debug)]
 pub struct Threads<'d, 'q>
 where
@@ -13,6 +18,7 @@ where
 {
     ptr: *mut ffi::notmuch_threads_t,
     marker: ScopedPhantomcow<'q, Query<'d>>,
+    database: Database,
 }
 
 impl<'d, 'q> Drop for Threads<'d, 'q>
@@ -28,13 +34,14 @@ impl<'d, 'q> Threads<'d, 'q>
 where
     'd: 'q,
 {
-    pub(crate) fn from_ptr<P>(ptr: *mut ffi::notmuch_threads_t, owner: P) -> Threads<'d, 'q>
+    pub(crate) fn from_ptr<P>(ptr: *mut ffi::notmuch_threads_t, owner: P, database: Database) -> Threads<'d, 'q>
     where
         P: Into<ScopedPhantomcow<'q, Query<'d>>>,
     {
         Threads {
             ptr,
             marker: owner.into(),
+            database,
         }
     }
 }
@@ -58,7 +65,7 @@ where
             thrd
         };
 
-        Some(Thread::from_ptr(cthrd, ScopedPhantomcow::<'q, Query<'d>>::share(&mut self.marker)))
+        Some(Thread::from_ptr(cthrd, ScopedPhantomcow::<'q, Query<'d>>::share(&mut self.marker), self.database.clone()))
     }
 }
 
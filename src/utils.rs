@@ -1,6 +1,9 @@
 use libc;
 use std::{ffi, str};
 use std::borrow::Cow;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
 use supercow::{Supercow, DefaultFeatures/*, NonSyncFeatures*/};
 use supercow::ext::{BoxedStorage};
 
@@ -27,6 +30,44 @@ impl ToStr for *const libc::c_char {
     }
 }
 
+/// Paths are raw bytes and need not be valid UTF-8.
+pub trait ToPath {
+    fn to_path<'a>(&self) -> &'a Path;
+}
+
+impl ToPath for *const libc::c_char {
+    #[cfg(unix)]
+    fn to_path<'a>(&self) -> &'a Path {
+        Path::new(ffi::OsStr::from_bytes(unsafe { ffi::CStr::from_ptr(*self) }.to_bytes()))
+    }
+
+    // Elsewhere a path can only be borrowed from valid UTF-8.
+    #[cfg(not(unix))]
+    fn to_path<'a>(&self) -> &'a Path {
+        Path::new(self.to_str().expect("path is not valid UTF-8"))
+    }
+}
+
+/// Convert a path to a `CString` byte for byte, without requiring it to be
+/// valid UTF-8.
+#[cfg(unix)]
+pub fn path_to_cstring<P>(path: &P) -> Result<ffi::CString, ffi::NulError>
+where
+    P: AsRef<Path> + ?Sized,
+{
+    ffi::CString::new(path.as_ref().as_os_str().as_bytes())
+}
+
+/// Convert a path to a `CString`, replacing anything that is not valid
+/// UTF-8.
+#[cfg(not(unix))]
+pub fn path_to_cstring<P>(path: &P) -> Result<ffi::CString, ffi::NulError>
+where
+    P: AsRef<Path> + ?Sized,
+{
+    ffi::CString::new(path.as_ref().to_string_lossy().into_owned())
+}
+
 pub trait ToString {
     fn to_string(&self) -> String;
 }
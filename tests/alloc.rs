@@ -0,0 +1,116 @@
+//! Heap accounting for the iterators, in a binary of its own so that no
+//! other test allocates while these measure.
+//!
+//! notmuch allocates through talloc and Xapian rather than the Rust
+//! allocator, so the heap is read from glibc's `mallinfo2` instead.
+#![cfg(all(target_os = "linux", target_env = "gnu"))]
+
+extern crate dirs;
+extern crate tempfile;
+extern crate libc;
+extern crate notmuch;
+extern crate gethostname;
+extern crate maildir;
+extern crate lettre;
+extern crate lettre_email;
+
+// Only part of the fixtures is used here.
+#[allow(dead_code)]
+mod fixtures;
+
+use std::sync::{Mutex, MutexGuard};
+
+use fixtures::MailBox;
+
+// These tests share the process heap, so they must not run concurrently.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial() -> MutexGuard<'static, ()> {
+    SERIAL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Bytes currently allocated through malloc, including mmap'ed chunks.
+fn heap_in_use() -> usize {
+    let info = unsafe { libc::mallinfo2() };
+    info.uordblks + info.hblkhd
+}
+
+fn database_with(count: usize) -> (MailBox, notmuch::Database) {
+    let mailbox = MailBox::new();
+    let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    for i in 0..count {
+        let raw = format!("From: src@example.com\nTo: dst@example.com\nSubject: Bulk {}\nMessage-ID: <bulk{}@example.com>\n\nbody\n", i, i);
+        let filename = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        database.index_file(&filename, None).unwrap();
+    }
+
+    (mailbox, database)
+}
+
+// How far the heap grows past its level after the first few messages
+// while the rest are walked.
+fn growth<I>(mut messages: I) -> usize
+where
+    I: Iterator,
+{
+    messages.by_ref().take(10).for_each(drop);
+
+    let baseline = heap_in_use();
+    let mut peak = baseline;
+    for message in messages {
+        drop(message);
+        peak = peak.max(heap_in_use());
+    }
+    peak - baseline
+}
+
+#[test]
+fn test_search_messages_lazy_bounded() {
+    let _serial = serial();
+    let (_mailbox, database) = database_with(500);
+
+    let query = database.create_query("*").unwrap();
+    let eager = growth(query.search_messages().unwrap());
+
+    let query = database.create_query("*").unwrap();
+    let lazy = growth(query.search_messages_lazy().unwrap());
+
+    // The eager iterator keeps every message until the query goes away;
+    // the lazy one frees each as it goes.
+    assert!(eager > 0);
+    assert!(lazy * 4 < eager, "lazy iteration grew the heap by {} bytes, eager by {}", lazy, eager);
+}
+
+#[test]
+fn test_drop_mid_iteration_frees() {
+    let _serial = serial();
+    let (_mailbox, database) = database_with(50);
+
+    // Take one message and one thread, then drop the query together with
+    // the half-walked iterators.
+    let round = || {
+        let query = database.create_query("*").unwrap();
+        let mut messages = query.search_messages().unwrap();
+        assert!(messages.next().is_some());
+        let mut threads = query.search_threads().unwrap();
+        assert!(threads.next().is_some());
+    };
+
+    // Let Xapian fill its caches first.
+    for _ in 0..10 {
+        round();
+    }
+
+    const ROUNDS: usize = 200;
+    let before = heap_in_use();
+    for _ in 0..ROUNDS {
+        round();
+    }
+    let after = heap_in_use();
+
+    // Every talloc chunk alone takes more than 64 bytes, so leaking any of
+    // the query, its results or the messages would exceed this.
+    let leaked = after.saturating_sub(before);
+    assert!(leaked < ROUNDS * 64, "{} bytes leaked over {} rounds", leaked, ROUNDS);
+}
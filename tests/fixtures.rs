@@ -149,6 +149,14 @@ impl MailBox {
 
         Ok((msg_id, msgpath))
     }
+
+    /// Deliver a raw, preformatted mail message in the mbox.
+    /// Like `deliver`, this does not insert it into the notmuch database.
+    /// returns the pathname.
+    pub fn deliver_raw(&self, data: &[u8]) -> Result<PathBuf> {
+        let id = self.maildir.store_new(data).unwrap();
+        Ok(self.path().join("new").join(&id))
+    }
 }
 
 impl Drop for MailBox {
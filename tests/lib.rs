@@ -5,6 +5,12 @@ extern crate gethostname;
 extern crate maildir;
 extern crate lettre;
 extern crate lettre_email;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "time")]
+extern crate time;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
 mod fixtures;
 mod test_database;
@@ -12,4 +18,7 @@ mod test_query;
 mod test_thread;
 mod test_message;
 mod test_tags;
+mod test_index;
+mod test_error;
+mod test_capabilities;
 
@@ -0,0 +1,82 @@
+use fixtures::MailBox;
+
+#[test]
+fn test_library_covers_build() {
+    let caps = notmuch::capabilities();
+    let built = notmuch::built_features();
+
+    // The library was linked against, so it must offer at least what this
+    // build uses.
+    assert!(caps.notmuch_version >= built.notmuch_version);
+    assert!(caps.revision || !built.revision);
+    assert!(caps.count_files || !built.count_files);
+    assert!(caps.open_with_config || !built.open_with_config);
+    assert!(caps.reopen || !built.reopen);
+    assert!(caps.query_syntax || !built.query_syntax);
+    assert_eq!(caps.compact, built.compact);
+}
+
+#[test]
+fn test_gated_methods() {
+    let caps = notmuch::capabilities();
+    let built = notmuch::built_features();
+    let mailbox = MailBox::new();
+    let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+    let db = notmuch::Database::create(&mailbox.path()).unwrap();
+    let (message, _) = db.index_file(&filename, None).unwrap();
+
+    // Whatever the build reports as available must work with the linked
+    // library, and the rest must be reported missing from the build.
+    #[cfg(feature = "v0_21")]
+    assert!(caps.revision && db.revision().revision > 0);
+    #[cfg(not(feature = "v0_21"))]
+    assert!(!built.revision);
+
+    #[cfg(feature = "v0_26")]
+    assert!(caps.count_files && message.count_files().unwrap() == 1);
+    #[cfg(not(feature = "v0_26"))]
+    assert!(!built.count_files);
+
+    #[cfg(feature = "v0_32")]
+    {
+        let config = mailbox.path().join("notmuch-config");
+        let opened = notmuch::Database::open_with_config(None, notmuch::DatabaseMode::ReadOnly, Some(&config), None);
+        assert!(caps.open_with_config && opened.is_ok());
+    }
+    #[cfg(not(feature = "v0_32"))]
+    assert!(!built.open_with_config);
+
+    #[cfg(feature = "v0_35")]
+    {
+        assert!(caps.query_syntax);
+        db.create_query_with_syntax("*", notmuch::QuerySyntax::Xapian).unwrap();
+        assert!(caps.reopen);
+        db.reopen(notmuch::DatabaseMode::ReadOnly).unwrap();
+    }
+    #[cfg(not(feature = "v0_35"))]
+    assert!(!built.reopen && !built.query_syntax);
+
+    let _ = (caps, built);
+    drop(message);
+}
+
+#[cfg(feature = "v0_26")]
+#[test]
+fn test_compact_matches_built_with() {
+    let mailbox = MailBox::new();
+    notmuch::Database::create(&mailbox.path()).unwrap().close().unwrap();
+
+    let result = notmuch::Database::compact(&mailbox.path(), None);
+    if notmuch::capabilities().compact {
+        result.unwrap();
+    } else {
+        assert_eq!(result.unwrap_err().status(), Some(notmuch::Status::UnsupportedOperation));
+    }
+}
+
+#[test]
+fn test_built_with_names() {
+    assert_eq!(notmuch::built_with("compact"), notmuch::capabilities().compact);
+    assert!(!notmuch::built_with("no_such_feature"));
+    assert!(!notmuch::built_with("com\0pact"));
+}
@@ -1,3 +1,5 @@
+use std::fs;
+use std::path::PathBuf;
 use fixtures::{NotmuchCommand, MailBox};
 
 // #[test]
@@ -43,6 +45,54 @@ mod database {
     }
 
 
+    #[test]
+    fn test_opener(){
+        let mailbox = MailBox::new();
+        notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let db = notmuch::Database::opener().path(mailbox.path()).open().unwrap();
+        assert_eq!(db.path(), mailbox.path());
+        assert_eq!(db.mode(), notmuch::DatabaseMode::ReadOnly);
+    }
+
+    #[cfg(feature = "v0_32")]
+    #[test]
+    fn test_opener_config(){
+        let mailbox = MailBox::new();
+        notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let db = notmuch::DatabaseOpener::new()
+            .config(mailbox.path().join("notmuch-config"))
+            .mode(notmuch::DatabaseMode::ReadWrite)
+            .open()
+            .unwrap();
+        assert_eq!(db.path(), mailbox.path());
+        assert_eq!(db.mode(), notmuch::DatabaseMode::ReadWrite);
+    }
+
+    #[test]
+    fn test_open_nonexistent(){
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly);
+        assert!(db.is_err());
+        assert!(!mailbox.path().join(".notmuch").exists());
+
+        let db = notmuch::Database::open(&mailbox.path().join("missing"), notmuch::DatabaseMode::ReadWrite);
+        assert!(db.is_err());
+    }
+
+    #[test]
+    fn test_open_error_detail(){
+        let mailbox = MailBox::new();
+        let err = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap_err();
+
+        assert!(err.status().is_some());
+        let detail = err.detail().unwrap();
+        assert!(!detail.is_empty());
+        assert!(err.to_string().ends_with(detail));
+    }
+
+
     #[test]
     fn test_close(){
         let mailbox = MailBox::new();
@@ -51,6 +101,112 @@ mod database {
         assert!(db.close().is_ok());
     }
 
+    #[cfg(feature = "v0_32")]
+    #[test]
+    fn test_open_with_config(){
+        let mailbox = MailBox::new();
+        notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let config = mailbox.path().join("notmuch-config");
+        let db = notmuch::Database::open_with_config(None, notmuch::DatabaseMode::ReadOnly, Some(&config), None).unwrap();
+
+        assert_eq!(db.path(), mailbox.path());
+        assert_eq!(db.config("user.name").unwrap(), Some("Some Hacker".to_string()));
+    }
+
+    #[cfg(feature = "v0_32")]
+    #[test]
+    fn test_create_with_config(){
+        use std::process::Command;
+
+        let mailbox = MailBox::new();
+        let config = mailbox.path().join("custom-config");
+        fs::write(&config, format!("[database]\npath={}\n[new]\ntags=custom;fresh\n", mailbox.path().display())).unwrap();
+
+        let db = notmuch::Database::create_with_config(None, Some(&config), None).unwrap();
+        assert_eq!(db.path(), mailbox.path());
+        assert_eq!(db.mode(), notmuch::DatabaseMode::ReadWrite);
+        assert_eq!(db.config_values("new.tags").unwrap(), vec!["custom", "fresh"]);
+        db.close().unwrap();
+
+        let (msgid, _) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let status = Command::new("notmuch").env("NOTMUCH_CONFIG", &config).arg("new").status().unwrap();
+        assert!(status.success());
+
+        let db = notmuch::Database::open_with_config(None, notmuch::DatabaseMode::ReadOnly, Some(&config), None).unwrap();
+        let message = db.find_message(&msgid).unwrap().unwrap();
+        assert_eq!(message.tags().into_sorted_vec(), vec!["custom", "fresh"]);
+    }
+
+    #[cfg(feature = "v0_32")]
+    #[test]
+    fn test_create_with_config_existing(){
+        let mailbox = MailBox::new();
+        notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let config = mailbox.path().join("notmuch-config");
+        assert!(notmuch::Database::create_with_config(None, Some(&config), None).is_err());
+    }
+
+    #[cfg(feature = "v0_35")]
+    #[test]
+    fn test_reopen(){
+        let mailbox = MailBox::new();
+        notmuch::Database::create(&mailbox.path()).unwrap();
+        let reader = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+
+        let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        {
+            let writer = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadWrite).unwrap();
+            writer.index_file(&filename, None).unwrap();
+            writer.close().unwrap();
+        }
+
+        reader.reopen(notmuch::DatabaseMode::ReadOnly).unwrap();
+        assert!(reader.find_message(&msgid).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_send_across_threads(){
+        use std::thread;
+
+        let mailbox = MailBox::new();
+        mailbox.deliver(None, Some("foo".to_string()), None, None, vec![], true, None, false, false, false).unwrap();
+        mailbox.deliver(None, Some("bar".to_string()), None, None, vec![], true, None, false, false, false).unwrap();
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+
+        // Hand the database from thread to thread, never using it from
+        // two of them at once.
+        let db = thread::spawn(move || {
+            assert_eq!(db.create_query("foo").unwrap().count_messages().unwrap(), 1);
+            db
+        }).join().unwrap();
+
+        let db = thread::spawn(move || {
+            let ids: Vec<String> = db.search_messages("bar").unwrap().map(|m| m.id().into_owned()).collect();
+            assert_eq!(ids.len(), 1);
+            db
+        }).join().unwrap();
+
+        assert_eq!(db.create_query("*").unwrap().count_messages().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_clone_shares_handle(){
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        let clone = db.clone();
+        drop(db);
+
+        // The handle stays open until the last clone is dropped.
+        assert_eq!(clone.path(), mailbox.path());
+        clone.set_config("test.key", "value").unwrap();
+        assert_eq!(clone.config("test.key").unwrap(), Some("value".to_string()));
+    }
+
     #[test]
     fn test_drop_noclose(){
         let mailbox = MailBox::new();
@@ -60,11 +216,51 @@ mod database {
     }
 
     #[test]
-    fn test_close_drop(){
+    fn test_close_persists(){
         let mailbox = MailBox::new();
+        let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+
         let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        db.index_file(&filename, None).unwrap().0.add_tag("persisted").unwrap();
         db.close().unwrap();
-        drop(db);
+
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let msg = db.find_message(&msgid).unwrap().unwrap();
+        assert!(msg.tags().any(|t| t == "persisted"));
+    }
+
+    #[test]
+    fn test_compact(){
+        let mailbox = MailBox::new();
+        {
+            let db = notmuch::Database::create(&mailbox.path()).unwrap();
+            let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+            db.index_file(&filename, None).unwrap();
+        }
+
+        let backup = mailbox.path().join("backup");
+        let mut messages = vec![];
+        notmuch::Database::compact_with_status(&mailbox.path(), Some(&backup), |msg: &str| messages.push(msg.to_string())).unwrap();
+
+        assert!(!messages.is_empty());
+        assert!(backup.read_dir().unwrap().next().is_some());
+
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        assert_eq!(db.create_query("*").unwrap().count_messages().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_upgrade_noop(){
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        assert!(!db.needs_upgrade());
+        db.upgrade().unwrap();
+
+        let mut calls = 0;
+        db.upgrade_with_status(|_progress| calls += 1).unwrap();
+        assert_eq!(calls, 0);
+        assert!(!db.needs_upgrade());
     }
 
     #[test]
@@ -74,6 +270,15 @@ mod database {
         assert_eq!(db.path(), mailbox.path());
     }
 
+    #[test]
+    fn test_path_open(){
+        let mailbox = MailBox::new();
+        notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        assert_eq!(db.path(), mailbox.path());
+    }
+
     #[test]
     fn test_version(){
         let mailbox = MailBox::new();
@@ -81,14 +286,142 @@ mod database {
         assert!(db.version() > 0);
     }
 
+    #[test]
+    fn test_mode() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        assert_eq!(db.mode(), notmuch::DatabaseMode::ReadWrite);
+        db.close().unwrap();
+
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        assert_eq!(db.mode(), notmuch::DatabaseMode::ReadOnly);
+    }
 }
 
 
 mod atomic {
-    // use super::*;
+    use super::*;
+
+    fn index_many(mailbox: &MailBox, db: &notmuch::Database, count: usize) {
+        for _ in 0..count {
+            let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+            db.index_file(&filename, None).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_bulk_tag() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        index_many(&mailbox, &db, 100);
+
+        let atomic = db.begin_atomic().unwrap();
+        let query = db.create_query("*").unwrap();
+        for msg in query.search_messages().unwrap() {
+            msg.add_tag("bulk").unwrap();
+        }
+        atomic.end().unwrap();
+
+        let query = db.create_query("tag:bulk").unwrap();
+        assert_eq!(query.count_messages().unwrap(), 100);
+    }
+
+    #[test]
+    fn test_add_tag_all() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        index_many(&mailbox, &db, 50);
+
+        let query = db.create_query("*").unwrap();
+        assert_eq!(db.add_tag_all(query.search_messages().unwrap(), "bulk").unwrap(), 50);
+        assert_eq!(db.create_query("tag:bulk").unwrap().count_messages().unwrap(), 50);
 
-    // TODO: how do I test this??
+        // Messages that already carry the tag are not counted again.
+        assert_eq!(db.add_tag_all(query.search_messages().unwrap(), "bulk").unwrap(), 0);
 
+        let query = db.create_query("tag:bulk").unwrap();
+        assert_eq!(db.remove_tag_all(query.search_messages().unwrap(), "bulk").unwrap(), 50);
+        assert_eq!(db.create_query("tag:bulk").unwrap().count_messages().unwrap(), 0);
+    }
+
+    // notmuch only advances the committed revision when an atomic section
+    // ends, so a single section shows up as a single revision step.
+    #[cfg(feature = "v0_21")]
+    #[test]
+    fn test_add_tag_all_single_atomic() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        index_many(&mailbox, &db, 50);
+        let query = db.create_query("*").unwrap();
+
+        let before = db.revision().revision;
+        assert_eq!(db.add_tag_all(query.search_messages().unwrap(), "bulk").unwrap(), 50);
+        assert_eq!(db.revision().revision, before + 1);
+        assert_eq!(db.atomic_depth(), 0);
+
+        // Tagging one message at a time commits every change separately.
+        let before = db.revision().revision;
+        for message in query.search_messages().unwrap() {
+            message.add_tag("single").unwrap();
+        }
+        assert_eq!(db.revision().revision, before + 50);
+    }
+
+    #[test]
+    fn test_nested() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        index_many(&mailbox, &db, 2);
+
+        {
+            let _outer = db.begin_atomic().unwrap();
+            let inner = db.begin_atomic().unwrap();
+            assert_eq!(db.atomic_depth(), 2);
+
+            let query = db.create_query("*").unwrap();
+            for msg in query.search_messages().unwrap() {
+                msg.add_tag("nested").unwrap();
+            }
+            inner.end().unwrap();
+            assert_eq!(db.atomic_depth(), 1);
+        }
+        assert_eq!(db.atomic_depth(), 0);
+
+        let query = db.create_query("tag:nested").unwrap();
+        assert_eq!(query.count_messages().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_atomic_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _outer = db.begin_atomic().unwrap();
+            let _inner = db.begin_atomic().unwrap();
+            panic!("interrupted inside an atomic operation");
+        }));
+        assert!(res.is_err());
+        assert_eq!(db.atomic_depth(), 0);
+
+        // The database is usable for a fresh, balanced operation.
+        db.begin_atomic().unwrap().end().unwrap();
+    }
+
+    #[test]
+    fn test_begin_atomic_read_only() {
+        let mailbox = MailBox::new();
+        notmuch::Database::create(&mailbox.path()).unwrap();
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+
+        match db.begin_atomic() {
+            Err(notmuch::Error::NotmuchError(notmuch::Status::ReadOnlyDatabase, _)) => (),
+            other => panic!("expected ReadOnlyDatabase, got {:?}", other),
+        }
+        assert_eq!(db.atomic_depth(), 0);
+    }
 }
 
 
@@ -150,7 +483,21 @@ mod revision {
 
     }
 
-    // TODO: add tests for revisions comparisons
+    #[test]
+    fn test_tag_increases(){
+        let db = DatabaseFixture::new();
+
+        let rev0 = db.database.revision();
+
+        let query = db.database.create_query("*").unwrap();
+        let msg = query.search_messages().unwrap().next().unwrap();
+        msg.add_tag("foo").unwrap();
+
+        let rev1 = db.database.revision();
+
+        assert!(rev1.revision > rev0.revision);
+        assert_eq!(rev0.uuid, rev1.uuid);
+    }
 
 }
  
@@ -164,13 +511,45 @@ mod messages {
         let db = notmuch::Database::create(&mailbox.path()).unwrap();
 
         let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
-        let msg = db.index_file(&filename, None).unwrap();
+        let (msg, _) = db.index_file(&filename, None).unwrap();
 
         assert_eq!(msg.filename(), filename);
         assert_eq!(msg.id(), msgid);
         
     }
 
+    #[test]
+    fn test_index_file_duplicate() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let raw = "From: src@example.com\nTo: dst@example.com\nSubject: Dup\nMessage-ID: <dup@example.com>\n\nbody\n";
+        let filename0 = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        let filename1 = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+
+        let (msg0, dup0) = db.index_file(&filename0, None).unwrap();
+        assert!(!dup0);
+        assert_eq!(msg0.id(), "dup@example.com");
+
+        let (msg1, dup1) = db.index_file(&filename1, None).unwrap();
+        assert!(dup1);
+        assert_eq!(msg1.id(), "dup@example.com");
+    }
+
+    #[test]
+    fn test_index_file_read_only() {
+        let mailbox = MailBox::new();
+        notmuch::Database::create(&mailbox.path()).unwrap();
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let res = db.index_file(&filename, None).map(|_| ());
+        match res {
+            Err(notmuch::Error::NotmuchError(notmuch::Status::ReadOnlyDatabase, _)) => (),
+            other => panic!("expected ReadOnlyDatabase, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_remove_message() {
         let mailbox = MailBox::new();
@@ -183,6 +562,24 @@ mod messages {
         db.remove_message(&filename).unwrap();
         assert!(db.find_message(&msgid).unwrap().is_none());
     }
+
+    #[test]
+    fn test_remove_message_duplicate() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let raw = "From: src@example.com\nTo: dst@example.com\nSubject: Dup\nMessage-ID: <dup@example.com>\n\nbody\n";
+        let filename0 = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        let filename1 = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        db.index_file(&filename0, None).unwrap();
+        db.index_file(&filename1, None).unwrap();
+
+        assert!(db.remove_message(&filename0).unwrap());
+        assert!(db.find_message("dup@example.com").unwrap().is_some());
+
+        assert!(!db.remove_message(&filename1).unwrap());
+        assert!(db.find_message("dup@example.com").unwrap().is_none());
+    }
     
     #[test]
     fn test_find_message() {
@@ -190,7 +587,7 @@ mod messages {
         let db = notmuch::Database::create(&mailbox.path()).unwrap();
 
         let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
-        let msg0 = db.index_file(&filename, None).unwrap();
+        let (msg0, _) = db.index_file(&filename, None).unwrap();
         
         let msg1 = db.find_message(&msgid).unwrap().unwrap();
         assert_eq!(msg0.id(), msgid);
@@ -207,6 +604,75 @@ mod messages {
 
         assert!(db.find_message(&"foo").unwrap().is_none());
     }
+
+    #[test]
+    fn test_search_shorthand() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        let (inbox, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.index_file(&filename, None).unwrap().0.add_tag("inbox").unwrap();
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.index_file(&filename, None).unwrap();
+
+        let ids: Vec<String> = db.search_messages("tag:inbox").unwrap().map(|m| m.id().into_owned()).collect();
+        assert_eq!(ids, vec![inbox]);
+        assert_eq!(db.search_threads("*").unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_all_messages() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        for _ in 0..5 {
+            let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+            db.index_file(&filename, None).unwrap();
+        }
+
+        assert_eq!(db.all_messages().unwrap().count(), 5);
+        assert_eq!(db.all_threads().unwrap().count(), 5);
+    }
+
+    #[test]
+    fn test_find_message_by_filename() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.index_file(&filename, None).unwrap();
+
+        let msg = db.find_message_by_filename(&filename).unwrap().unwrap();
+        assert_eq!(msg.id(), msgid);
+        assert_eq!(msg.filename(), filename);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_message_by_filename_non_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let filename = mailbox.path().join("cur").join(OsStr::from_bytes(b"odd\xff:2,"));
+        fs::write(&filename, "From: src@example.com\nTo: dst@example.com\nSubject: Odd\nMessage-ID: <odd@example.com>\n\nbody\n").unwrap();
+        db.index_file(&filename, None).unwrap();
+
+        let msg = db.find_message_by_filename(&filename).unwrap().unwrap();
+        assert_eq!(msg.id(), "odd@example.com");
+
+        // A lossy conversion of the name must not match.
+        let lossy = mailbox.path().join("cur").join(filename.file_name().unwrap().to_string_lossy().as_ref());
+        assert!(db.find_message_by_filename(&lossy).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_message_by_filename_notfound() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        assert!(db.find_message_by_filename(&mailbox.path().join("new").join("foo")).unwrap().is_none());
+    }
     
 }
 
@@ -228,7 +694,7 @@ mod tags {
         let mailbox = MailBox::new();
         let db = notmuch::Database::create(&mailbox.path()).unwrap();
         let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
-        let msg = db.index_file(&filename, None).unwrap();
+        let (msg, _) = db.index_file(&filename, None).unwrap();
 
         msg.add_tag(&"hello").unwrap();
         let tags: Vec<String> = db.all_tags().unwrap().collect();
@@ -247,6 +713,202 @@ mod tags {
         assert_eq!(t1, t2);
     }
 
+    #[test]
+    fn test_several() {
+        let db = DatabaseFixture::new();
+
+        let query = db.database.create_query("*").unwrap();
+        for (i, msg) in query.search_messages().unwrap().enumerate() {
+            msg.add_tag(&format!("tag{}", i)).unwrap();
+        }
+
+        let mut tags: Vec<String> = db.database.all_tags().unwrap().collect();
+        tags.sort();
+        assert_eq!(tags, vec!["inbox", "tag0", "tag1", "tag2", "unread"]);
+    }
+
+}
+
+mod config {
+    use super::*;
+
+    #[cfg(feature = "v0_32")]
+    #[test]
+    fn test_config_values() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        db.set_config("search.exclude_tags", "deleted;spam").unwrap();
+        assert_eq!(db.config_values("search.exclude_tags").unwrap(), vec!["deleted", "spam"]);
+    }
+
+    #[test]
+    fn test_set_config_read_only() {
+        let mailbox = MailBox::new();
+        notmuch::Database::create(&mailbox.path()).unwrap();
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+
+        match db.set_config("user.name", "Somebody") {
+            Err(notmuch::Error::NotmuchError(notmuch::Status::ReadOnlyDatabase, _)) => (),
+            other => panic!("expected ReadOnlyDatabase, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        db.set_config("test.custom", "hello").unwrap();
+        assert_eq!(db.config("test.custom").unwrap(), Some("hello".to_string()));
+    }
+
+    #[test]
+    fn test_unset() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        assert_eq!(db.config("test.unset").unwrap(), None);
+    }
+
+    #[test]
+    fn test_list_prefix() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        db.set_config("test.a", "1").unwrap();
+        db.set_config("test.b", "2").unwrap();
+        db.set_config("other.c", "3").unwrap();
+
+        let mut pairs: Vec<(String, String)> = db.config_list("test.").unwrap().collect();
+        pairs.sort();
+        assert_eq!(pairs, vec![
+            ("test.a".to_string(), "1".to_string()),
+            ("test.b".to_string(), "2".to_string()),
+        ]);
+
+        let all: Vec<(String, String)> = db.config_list("").unwrap().collect();
+        assert!(all.iter().any(|(k, v)| k == "other.c" && v == "3"));
+        assert!(all.iter().any(|(k, _)| k == "test.a"));
+    }
+
+    #[test]
+    fn test_config_list_suffixes() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        db.set_config("maildir.synchronize_flags", "true").unwrap();
+        db.set_config("other.c", "3").unwrap();
+
+        let pairs = db.config_list_suffixes("maildir.").unwrap();
+        assert_eq!(pairs, vec![("synchronize_flags".to_string(), "true".to_string())]);
+    }
+}
+
+mod directory {
+    use super::*;
+
+    #[test]
+    fn test_not_indexed() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        assert!(db.directory(&mailbox.path().join("nonexistent")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_mtime() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.index_file(&filename, None).unwrap();
+
+        let dir = db.directory(&mailbox.path().join("new")).unwrap().unwrap();
+        dir.set_mtime(1234567890).unwrap();
+        assert_eq!(dir.mtime(), 1234567890);
+
+        let dir = db.directory(&mailbox.path().join("new")).unwrap().unwrap();
+        assert_eq!(dir.mtime(), 1234567890);
+    }
+
+    #[test]
+    fn test_write_read_only() {
+        let mailbox = MailBox::new();
+        mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let db = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let dir = db.directory(&mailbox.path().join("new")).unwrap().unwrap();
+        let mtime = dir.mtime();
+
+        match dir.set_mtime(1234567890) {
+            Err(notmuch::Error::NotmuchError(notmuch::Status::ReadOnlyDatabase, _)) => (),
+            other => panic!("expected ReadOnlyDatabase, got {:?}", other),
+        }
+        assert_eq!(dir.mtime(), mtime);
+
+        match dir.delete() {
+            Err(notmuch::Error::NotmuchError(notmuch::Status::ReadOnlyDatabase, _)) => (),
+            other => panic!("expected ReadOnlyDatabase, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_needs_rescan() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        let dir_path = mailbox.path().join("new");
+        assert!(db.needs_rescan(&dir_path, 1234567890).unwrap());
+
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.index_file(&filename, None).unwrap();
+        db.directory(&dir_path).unwrap().unwrap().set_mtime(1234567890).unwrap();
+
+        assert!(!db.needs_rescan(&dir_path, 1234567890).unwrap());
+        assert!(db.needs_rescan(&dir_path, 1234567891).unwrap());
+    }
+
+    #[test]
+    fn test_delete() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let subdir = mailbox.path().join("sub");
+        fs::create_dir(&subdir).unwrap();
+        let filename = subdir.join("msg");
+        fs::write(&filename, "From: src@example.com\nTo: dst@example.com\nSubject: Sub\nMessage-ID: <sub@example.com>\n\nbody\n").unwrap();
+        db.index_file(&filename, None).unwrap();
+
+        let in_sub = |db: &notmuch::Database| db.create_query("path:sub").unwrap().count_messages().unwrap();
+        let children = |db: &notmuch::Database| -> Vec<PathBuf> {
+            db.directory(&mailbox.path()).unwrap().unwrap().child_directories().collect()
+        };
+        assert_eq!(in_sub(&db), 1);
+        assert!(children(&db).contains(&PathBuf::from("sub")));
+
+        // notmuch expects the files to be removed before their directory,
+        // as a maildir sync does.
+        db.remove_message(&filename).unwrap();
+        db.directory(&subdir).unwrap().unwrap().delete().unwrap();
+
+        assert_eq!(in_sub(&db), 0);
+        assert_eq!(db.search_messages("path:sub").unwrap().count(), 0);
+        assert!(!children(&db).contains(&PathBuf::from("sub")));
+        assert!(db.directory(&subdir).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_child_files() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.index_file(&filename, None).unwrap();
+
+        let dir = db.directory(&mailbox.path().join("new")).unwrap().unwrap();
+        let files: Vec<PathBuf> = dir.child_files().collect();
+        assert_eq!(files, vec![PathBuf::from(filename.file_name().unwrap())]);
+    }
 }
 
 struct DatabaseFixture {
@@ -280,6 +942,32 @@ impl DatabaseFixture {
 mod query {
     use super::*;
 
+    #[test]
+    fn test_search_error() {
+        let db = DatabaseFixture::new();
+
+        let query = db.database.create_query("AND").unwrap();
+        let err = query.search_messages().map(|_| ()).unwrap_err();
+        assert_eq!(err.status(), Some(notmuch::Status::XapianException));
+        assert!(!err.detail().unwrap().is_empty());
+        assert_eq!(err.detail().map(|d| d.to_string()), db.database.status_string());
+    }
+
+    #[test]
+    fn test_error_detail_not_stale() {
+        let db = DatabaseFixture::new();
+
+        let query = db.database.create_query("AND").unwrap();
+        let first = query.count_messages().unwrap_err();
+        assert!(first.detail().unwrap().contains("AND"));
+
+        // The next failure comes with its own message, not the first one.
+        let query = db.database.create_query("OR").unwrap();
+        let second = query.count_messages().unwrap_err();
+        assert!(second.detail().unwrap().contains("OR"));
+        assert!(!second.detail().unwrap().contains("AND"));
+    }
+
     #[test]
     fn test_count_messages() {
         let db = DatabaseFixture::new();
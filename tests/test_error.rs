@@ -0,0 +1,18 @@
+use std::error::Error;
+use std::ffi::CString;
+
+#[test]
+fn test_display_status() {
+    let err = notmuch::Error::NotmuchError(notmuch::Status::ReadOnlyDatabase, None);
+
+    assert_eq!(err.to_string(), "Attempt to write to a read-only database.");
+    assert_eq!(err.to_string(), notmuch::Status::ReadOnlyDatabase.to_string());
+    assert!(err.source().is_some());
+}
+
+#[test]
+fn test_display_nul() {
+    let err: notmuch::Error = CString::new("ta\0g").unwrap_err().into();
+
+    assert_eq!(err.to_string(), "Interior nul byte in string argument at position 2");
+}
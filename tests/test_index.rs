@@ -0,0 +1,36 @@
+use fixtures::MailBox;
+
+#[test]
+fn test_new() {
+    let mailbox = MailBox::new();
+    let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    let opts = notmuch::IndexOpts::new(&db).unwrap();
+    assert_eq!(opts.decrypt_policy(), db.default_indexopts().unwrap().decrypt_policy());
+
+    let policy = opts.set_decrypt_policy(notmuch::DecryptionPolicy::True).unwrap()
+        .decrypt_policy();
+    assert_eq!(policy, notmuch::DecryptionPolicy::True);
+
+    let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+    db.index_file(&filename, Some(opts)).unwrap();
+}
+
+#[test]
+fn test_try_clone() {
+    let mailbox = MailBox::new();
+    let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    let opts = notmuch::IndexOpts::new(&db).unwrap();
+    opts.set_decrypt_policy(notmuch::DecryptionPolicy::NoStash).unwrap();
+
+    let copy = opts.try_clone().unwrap();
+    assert_eq!(copy.decrypt_policy(), notmuch::DecryptionPolicy::NoStash);
+
+    copy.set_decrypt_policy(notmuch::DecryptionPolicy::False).unwrap();
+    assert_eq!(copy.decrypt_policy(), notmuch::DecryptionPolicy::False);
+    assert_eq!(opts.decrypt_policy(), notmuch::DecryptionPolicy::NoStash);
+
+    let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+    db.index_file(&filename, Some(copy)).unwrap();
+}
@@ -1,6 +1,7 @@
+use std::fs;
 use std::sync::Arc;
 use std::path::PathBuf;
-use fixtures::MailBox;
+use fixtures::{MailBox, NotmuchCommand};
 
 struct MessageFixture {
     // Return a single thread with 2 messages
@@ -17,7 +18,7 @@ impl MessageFixture {
         let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![],  true, None, false, false, false).unwrap();
 
         let database = Arc::new(notmuch::Database::create(&mailbox.path()).unwrap());
-        let message = <notmuch::Database as notmuch::DatabaseExt>::index_file(database.clone(), &filename, None).unwrap();
+        let (message, _) = <notmuch::Database as notmuch::DatabaseExt>::index_file(database.clone(), &filename, None).unwrap();
     
         Self {
             mailbox,
@@ -67,18 +68,355 @@ mod message {
         assert_eq!(names, vec![msg.maildir_msg.1]);
     }
 
+    #[test]
+    fn test_filenames_duplicate() {
+        let mailbox = MailBox::new();
+        let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let copy = mailbox.path().join("cur").join("copy:2,");
+        fs::copy(&filename, &copy).unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let message = database.find_message(&msgid).unwrap().unwrap();
+
+        let mut names: Vec<PathBuf> = message.filenames().collect();
+        names.sort();
+        let mut expected = vec![filename, copy];
+        expected.sort();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_existing_filenames() {
+        let mailbox = MailBox::new();
+        let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let copy = mailbox.path().join("cur").join("copy:2,");
+        fs::copy(&filename, &copy).unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+        fs::remove_file(&filename).unwrap();
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let message = database.find_message(&msgid).unwrap().unwrap();
+
+        assert_eq!(message.filenames().count(), 2);
+        assert_eq!(message.filenames().into_existing(), vec![copy.clone()]);
+        assert_eq!(message.existing_filenames(), vec![copy]);
+    }
+
+    #[cfg(feature = "v0_26")]
+    #[test]
+    fn test_count_files() {
+        let msg = MessageFixture::new();
+        assert_eq!(msg.message.count_files().unwrap(), 1);
+
+        let copy = msg.mailbox.path().join("cur").join("copy:2,");
+        fs::copy(&msg.maildir_msg.1, &copy).unwrap();
+        let (message, duplicate) = msg.database.index_file(&copy, None).unwrap();
+
+        assert!(duplicate);
+        assert_eq!(message.count_files().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_add_tag_read_only() {
+        let mailbox = MailBox::new();
+        let (msgid, _) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let message = database.find_message(&msgid).unwrap().unwrap();
+
+        match message.add_tag("readonly") {
+            Err(notmuch::Error::NotmuchError(notmuch::Status::ReadOnlyDatabase, _)) => (),
+            other => panic!("expected ReadOnlyDatabase, got {:?}", other),
+        }
+
+        let results = vec![
+            message.remove_tag("inbox"),
+            message.remove_all_tags(),
+            message.maildir_flags_to_tags(),
+            message.add_property("key", "value"),
+            message.remove_property("key", "value"),
+            message.remove_all_properties(None),
+        ];
+        for res in results {
+            match res {
+                Err(notmuch::Error::NotmuchError(notmuch::Status::ReadOnlyDatabase, _)) => (),
+                other => panic!("expected ReadOnlyDatabase, got {:?}", other),
+            }
+        }
+        assert!(message.tags().any(|t| t == "inbox"));
+    }
+
+    #[test]
+    fn test_reindex_nostash() {
+        let msg = MessageFixture::new();
+
+        let opts = msg.database.default_indexopts().unwrap();
+        opts.set_decrypt_policy(notmuch::DecryptionPolicy::NoStash).unwrap();
+        assert_eq!(opts.decrypt_policy(), notmuch::DecryptionPolicy::NoStash);
+
+        msg.message.reindex(opts).unwrap();
+        assert!(msg.database.find_message(&msg.maildir_msg.0).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_reindex_default() {
+        let msg = MessageFixture::new();
+
+        msg.message.reindex_default().unwrap();
+
+        let query = msg.database.create_query(&format!("id:{}", msg.maildir_msg.0)).unwrap();
+        assert_eq!(query.count_messages().unwrap(), 1);
+    }
+
+    #[cfg(feature = "v0_32")]
+    #[test]
+    fn test_reindex_and_apply_new_tags() {
+        let msg = MessageFixture::new();
+        msg.database.set_config("new.tags", "inbox;unread").unwrap();
+
+        msg.message.remove_all_tags().unwrap();
+        assert!(msg.message.tags_vec().is_empty());
+
+        msg.message.reindex_and_apply_new_tags().unwrap();
+        assert_eq!(msg.message.tags_vec(), vec!["inbox", "unread"]);
+    }
+
+    #[test]
+    fn test_batch_tags() {
+        let msg = MessageFixture::new();
+
+        msg.message.batch_tags().add_tag("a").add_tag("b").remove_tag("a").apply().unwrap();
+
+        let tags: Vec<String> = msg.message.tags().collect();
+        assert_eq!(tags, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn test_batch_tags_drop() {
+        let msg = MessageFixture::new();
+
+        {
+            let _batch = msg.message.batch_tags().add_tag("dropped");
+        }
+
+        assert!(msg.message.tags().any(|t| t == "dropped"));
+    }
+
+    #[test]
+    fn test_batch_tags_failure() {
+        let msg = MessageFixture::new();
+        msg.message.add_tag("kept").unwrap();
+
+        let res = msg.message.batch_tags().remove_tag("kept").add_tag("new").add_tag(&"x".repeat(300)).apply();
+        match res {
+            Err(notmuch::Error::NotmuchError(notmuch::Status::TagTooLong, _)) => (),
+            other => panic!("expected TagTooLong, got {:?}", other),
+        }
+
+        let stored = msg.database.find_message(&msg.maildir_msg.0).unwrap().unwrap();
+        assert_eq!(stored.tags().collect::<Vec<String>>(), vec!["kept".to_string()]);
+        assert_eq!(msg.message.tags().collect::<Vec<String>>(), vec!["kept".to_string()]);
+    }
+
+    #[test]
+    fn test_set_tags() {
+        let msg = MessageFixture::new();
+
+        msg.message.set_tags(["a", "b"].iter()).unwrap();
+        msg.message.set_tags(vec!["b".to_string(), "c".to_string()]).unwrap();
+
+        let mut tags: Vec<String> = msg.message.tags().collect();
+        tags.sort();
+        assert_eq!(tags, vec!["b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn test_reset_tags_from_maildir() {
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], false, None, true, true, true).unwrap();
+        let (message, _) = db.index_file(&filename, None).unwrap();
+
+        message.add_tag("custom").unwrap();
+        message.add_tag("unread").unwrap();
+        message.reset_tags_from_maildir().unwrap();
+
+        assert_eq!(message.tags_vec(), vec!["flagged", "replied"]);
+    }
+
+    #[test]
+    fn test_frozen_message() {
+        let msg = MessageFixture::new();
+
+        let frozen = notmuch::FrozenMessage::new(&msg.message).unwrap();
+        frozen.add_tag("one").unwrap();
+        frozen.add_tag("two").unwrap();
+        frozen.thaw().unwrap();
+
+        let stored = msg.database.find_message(&msg.maildir_msg.0).unwrap().unwrap();
+        let mut tags: Vec<String> = stored.tags().collect();
+        tags.sort();
+        assert_eq!(tags, vec!["one".to_string(), "two".to_string()]);
+    }
+
+    #[test]
+    // Messages hash by their immutable ID; the interior mutability is in
+    // bookkeeping that the hash ignores.
+    #[allow(clippy::mutable_key_type)]
+    fn test_hash_by_id() {
+        use std::collections::HashSet;
+
+        let msg = MessageFixture::new();
+        let by_id = msg.database.create_query(&format!("id:{}", msg.maildir_msg.0)).unwrap();
+        let all = msg.database.create_query("*").unwrap();
+
+        let mut messages = HashSet::new();
+        messages.extend(by_id.search_messages().unwrap());
+        messages.extend(all.search_messages().unwrap());
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages.iter().next().unwrap().id(), msg.message.id());
+    }
+
+    #[test]
+    fn test_display() {
+        let msg = MessageFixture::new();
+        assert_eq!(msg.message.to_string(), msg.message.id());
+    }
+
+    #[test]
+    fn test_database() {
+        let msg = MessageFixture::new();
+        assert_eq!(msg.message.database().path(), msg.mailbox.path());
+
+        // The handle is shared with the database the message came from.
+        let atomic = msg.message.database().begin_atomic().unwrap();
+        assert_eq!(msg.database.atomic_depth(), 1);
+        atomic.end().unwrap();
+        assert_eq!(msg.database.atomic_depth(), 0);
+    }
+
+    #[test]
+    fn test_database_mode() {
+        let mailbox = MailBox::new();
+        let (msgid, _) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let message = database.find_message(&msgid).unwrap().unwrap();
+        assert_eq!(message.database().mode(), notmuch::DatabaseMode::ReadOnly);
+    }
+
+    #[test]
+    fn test_thread() {
+        let mailbox = MailBox::new();
+        let (root, _) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let (reply, _) = mailbox.deliver(None, None, None, None, vec![("In-Reply-To".to_string(), format!("<{}>", root))], true, None, false, false, false).unwrap();
+        mailbox.deliver(None, None, None, None, vec![("In-Reply-To".to_string(), format!("<{}>", reply))], true, None, false, false, false).unwrap();
+        mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let message = database.find_message(&reply).unwrap().unwrap();
+        let thread = message.thread().unwrap().unwrap();
+
+        assert_eq!(thread.id(), message.thread_id());
+        assert_eq!(thread.total_messages(), 3);
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let mailbox = MailBox::new();
+        for _ in 0..3 {
+            mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        }
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let owned: Vec<notmuch::OwnedMessage> = {
+            let query = database.create_query("*").unwrap();
+            query.search_messages().unwrap()
+                .map(|m| m.into_owned().unwrap().unwrap())
+                .collect()
+        };
+        drop(database);
+
+        assert_eq!(owned.len(), 3);
+        for message in &owned {
+            assert!(!message.id().is_empty());
+            assert!(message.tags().any(|t| t == "inbox"));
+            assert_eq!(message.header("subject").unwrap().unwrap(), "Test mail");
+            assert!(message.date() > 0);
+        }
+    }
+
     #[test]
     fn test_header() {
         let msg = MessageFixture::new();
         assert_eq!(msg.message.header(&"from").unwrap().unwrap().to_string(), "<src@example.com>");
     }
 
+    #[test]
+    fn test_header_case_insensitive() {
+        let msg = MessageFixture::new();
+
+        // Message-ID is served from the index, To from the message file.
+        for names in &[["message-id", "Message-ID", "MESSAGE-ID"], ["to", "To", "TO"]] {
+            let values: Vec<Option<String>> = names.iter().map(|n| msg.message.header_owned(n).unwrap()).collect();
+            assert!(values[0].is_some());
+            assert_eq!(values[1], values[0]);
+            assert_eq!(values[2], values[0]);
+        }
+    }
+
+    #[test]
+    fn test_header_owned() {
+        let msg = MessageFixture::new();
+        let owned = msg.message.header_owned("from").unwrap();
+        assert_eq!(owned, msg.message.header("from").unwrap().map(|h| h.to_string()));
+        assert_eq!(owned, Some("<src@example.com>".to_string()));
+        assert_eq!(msg.message.header_owned("X-Nonexistent").unwrap(), None);
+    }
+
+    #[test]
+    fn test_interior_nul() {
+        let msg = MessageFixture::new();
+
+        match msg.message.add_tag("a\0b") {
+            Err(notmuch::Error::NulError(_)) => (),
+            other => panic!("expected NulError, got {:?}", other),
+        }
+        assert!(msg.message.remove_tag("a\0b").is_err());
+        assert!(msg.message.header("X-A\0B").is_err());
+        assert!(msg.message.properties("a\0b", true).is_err());
+        assert!(msg.database.find_message("a\0b").is_err());
+        assert!(msg.database.create_query("tag:a\0b").is_err());
+    }
+
     #[test]
     fn test_header_not_present() {
         let msg = MessageFixture::new();
         assert_eq!(msg.message.header(&"foo").unwrap(), None);
     }
 
+    #[test]
+    fn test_header_nonexistent() {
+        let msg = MessageFixture::new();
+        assert_eq!(msg.message.header("X-Nonexistent").unwrap(), None);
+    }
+
     #[test]
     fn test_freeze() {
         let msg = MessageFixture::new();
@@ -130,6 +468,36 @@ mod message {
         assert!(!copy2.tags().any(|x| x == "foo"));
     }
 
+    #[test]
+    fn test_thaw_unbalanced() {
+        let msg = MessageFixture::new();
+
+        let frozen = notmuch::FrozenMessage::new(&msg.message).unwrap();
+        msg.message.thaw().unwrap();
+        match frozen.thaw() {
+            Err(notmuch::Error::NotmuchError(notmuch::Status::UnbalancedFreezeThaw, _)) => (),
+            other => panic!("expected UnbalancedFreezeThaw, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_freeze_context_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let msg = MessageFixture::new();
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _frozen = notmuch::FrozenMessage::new(&msg.message).unwrap();
+            msg.message.add_tag("foo").unwrap();
+            panic!("interrupted while frozen");
+        }));
+        assert!(res.is_err());
+
+        // The guard thawed the message on the way out.
+        assert!(msg.message.thaw().is_err());
+        let copy = msg.database.find_message(&msg.message.id()).unwrap().unwrap();
+        assert!(copy.tags().any(|x| x == "foo"));
+    }
+
     #[test]
     fn test_freeze_context_err() {
         // not sure if this test is ok?
@@ -156,15 +524,178 @@ mod message {
         assert_eq!(msg.message.replies().count(), 0);
     }
 
+    #[test]
+    fn test_matched() {
+        let mailbox = MailBox::new();
+        let (root, _) = mailbox.deliver(None, Some("root".to_string()), None, None, vec![], true, None, false, false, false).unwrap();
+        let (reply, _) = mailbox.deliver(None, Some("needle".to_string()), None, None, vec![("In-Reply-To".to_string(), format!("<{}>", root))], true, None, false, false, false).unwrap();
+        mailbox.deliver(None, Some("other".to_string()), None, None, vec![("In-Reply-To".to_string(), format!("<{}>", reply))], true, None, false, false, false).unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let query = database.create_query("needle").unwrap();
+        let thread = query.search_threads().unwrap().next().unwrap();
+
+        let matched: Vec<String> = thread.messages()
+            .filter(|m| m.matched().unwrap())
+            .map(|m| m.id().into_owned())
+            .collect();
+        assert_eq!(thread.total_messages(), 3);
+        assert_eq!(matched, vec![reply]);
+    }
+
+    #[test]
+    fn test_replies_unthreaded() {
+        let mailbox = MailBox::new();
+        let (msgid, _) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        mailbox.deliver(None, None, None, None, vec![("In-Reply-To".to_string(), format!("<{}>", msgid))], true, None, false, false, false).unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+
+        // Found directly, the message knows nothing about its thread.
+        let message = database.find_message(&msgid).unwrap().unwrap();
+        assert_eq!(message.replies().count(), 0);
+        assert!(message.replies().collect_tags().is_err());
+
+        // Through its thread, the reply shows up.
+        let query = database.create_query(&format!("id:{}", msgid)).unwrap();
+        let thread = query.search_threads().unwrap().next().unwrap();
+        let root = thread.toplevel_messages().next().unwrap();
+        assert_eq!(root.replies().count(), 1);
+    }
+
+}
+
+
+#[cfg(unix)]
+mod raw {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn test_filename_non_utf8() {
+        let mailbox = MailBox::new();
+        let filename = mailbox.path().join("cur").join(OsStr::from_bytes(b"caf\xe9:2,"));
+        fs::write(&filename, dated_mail("Tue, 14 Oct 2025 12:30:00 +0200")).unwrap();
+
+        let database = notmuch::Database::create(&mailbox.path()).unwrap();
+        let (message, _) = database.index_file(&filename, None).unwrap();
+
+        assert_eq!(message.filename(), filename);
+        assert_eq!(message.filenames().collect::<Vec<_>>(), vec![filename.clone()]);
+        assert!(database.find_message_by_filename(&filename).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_header_values() {
+        let raw = "From: src@example.com\nReceived: from a\nSubject: Hops\nreceived: from b\nReceived: from c\n\tby d\nMessage-ID: <hops@example.com>\n\nReceived: not a header\n";
+        let (_mailbox, message) = index_raw(raw);
+
+        assert_eq!(message.header_values("Received").unwrap(), vec!["from a", "from b", "from c\tby d"]);
+        assert!(message.header_values("X-Missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_headers() {
+        let raw = "From: src@example.com\r\nReceived: from a\r\n\tby b\r\nSubject: A folded\r\n  subject\r\nReceived: from c\r\nMessage-ID: <headers@example.com>\r\n\r\nNot-A-Header: body\r\n";
+        let (_mailbox, message) = index_raw(raw);
+
+        assert_eq!(message.headers().unwrap(), vec![
+            ("From".to_string(), "src@example.com".to_string()),
+            ("Received".to_string(), "from a\tby b".to_string()),
+            ("Subject".to_string(), "A folded  subject".to_string()),
+            ("Received".to_string(), "from c".to_string()),
+            ("Message-ID".to_string(), "<headers@example.com>".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_header_bytes() {
+        let (_mailbox, message) = index_raw(&dated_mail("Tue, 14 Oct 2025 12:30:00 +0200"));
+
+        assert_eq!(message.header_bytes("Subject").unwrap(), Some(b"Dated mail".to_vec()));
+        assert_eq!(message.header_bytes("X-Missing").unwrap(), None);
+    }
+}
+
+fn index_raw(raw: &str) -> (MailBox, notmuch::Message<'static, notmuch::Database>) {
+    let mailbox = MailBox::new();
+    let filename = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+
+    let database = Arc::new(notmuch::Database::create(&mailbox.path()).unwrap());
+    let (message, _) = <notmuch::Database as notmuch::DatabaseExt>::index_file(database, &filename, None).unwrap();
+
+    (mailbox, message)
+}
+
+fn dated_mail(date: &str) -> String {
+    format!("From: src@example.com\nTo: dst@example.com\nSubject: Dated mail\nMessage-ID: <dated@example.com>\nDate: {}\n\nbody\n", date)
 }
 
+mod date {
+    use super::*;
+
+    #[test]
+    fn test_date() {
+        let (_mailbox, message) = index_raw(&dated_mail("Tue, 14 Oct 2025 12:30:00 +0200"));
+        assert_eq!(message.date(), 1760437800);
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod datetime {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_datetime() {
+        let (_mailbox, message) = index_raw(&dated_mail("Tue, 14 Oct 2025 12:30:00 +0200"));
+
+        assert_eq!(message.datetime(), Utc.with_ymd_and_hms(2025, 10, 14, 10, 30, 0).unwrap());
+        assert_eq!(message.datetime().timestamp(), message.date());
+    }
+
+    #[test]
+    fn test_datetime_before_epoch() {
+        let (_mailbox, message) = index_raw(&dated_mail("Wed, 31 Dec 1969 23:59:00 +0000"));
+
+        assert_eq!(message.date(), -60);
+        assert_eq!(message.datetime(), Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 0).unwrap());
+        assert_eq!(message.datetime().timestamp(), -60);
+    }
+}
+
+#[cfg(feature = "time")]
+mod date_offset {
+    use super::*;
+    use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+    #[test]
+    fn test_date_offset() {
+        let (_mailbox, message) = index_raw(&dated_mail("Tue, 14 Oct 2025 12:30:00 +0200"));
+
+        let expected = PrimitiveDateTime::new(
+            Date::from_calendar_date(2025, Month::October, 14).unwrap(),
+            Time::from_hms(10, 30, 0).unwrap(),
+        ).assume_utc();
+        assert_eq!(message.date_offset(), expected);
+    }
+
+    #[test]
+    fn test_date_offset_unparseable() {
+        let (_mailbox, message) = index_raw("From: src@example.com\nTo: dst@example.com\nSubject: Undated mail\nMessage-ID: <undated@example.com>\n\nbody\n");
+
+        assert_eq!(message.date(), 0);
+        assert_eq!(message.date_offset(), OffsetDateTime::UNIX_EPOCH);
+    }
+}
 
-//      def test_date(self, msg):
-//          # XXX Someone seems to treat things as local time instead of
-//          #     UTC or the other way around.
-//          now = int(time.time())
-//          assert abs(now - msg.date) < 3600*24
- 
 mod properties {
     use super::*;
 
@@ -186,7 +717,7 @@ mod properties {
 
         assert_eq!(msg.message.property(&"foo").unwrap(), "bar");
 
-        let props = msg.message.properties(&"foo", true);
+        let props = msg.message.properties(&"foo", true).unwrap();
         let expect = vec![("foo", "bar"), ("foo", "baz")];
         for (&(ek, ev), (pk, pv)) in expect.iter().zip(props) {
             assert_eq!(ek, pk);
@@ -194,6 +725,38 @@ mod properties {
         }
     }
 
+    #[test]
+    fn test_iter_same_key() {
+        let msg = MessageFixture::new();
+        msg.message.add_property("foo", "a").unwrap();
+        msg.message.add_property("foo", "b").unwrap();
+        msg.message.add_property("foo", "c").unwrap();
+
+        let props: Vec<(String, String)> = msg.message.properties("foo", true).unwrap().collect();
+        assert_eq!(props.len(), 3);
+        assert_eq!(props, vec![
+            ("foo".to_string(), "a".to_string()),
+            ("foo".to_string(), "b".to_string()),
+            ("foo".to_string(), "c".to_string()),
+        ]);
+
+        assert_eq!(msg.message.properties("foo", true).unwrap().collect_values(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_get_first_and_all() {
+        let msg = MessageFixture::new();
+        msg.message.add_property("session-key", "a").unwrap();
+        msg.message.add_property("session-key", "b").unwrap();
+        msg.message.add_property("session-keys", "c").unwrap();
+
+        assert_eq!(msg.message.get_first("session-key").unwrap(), Some("a".to_string()));
+        assert_eq!(msg.message.get_all("session-key").unwrap(), vec!["a", "b"]);
+
+        assert_eq!(msg.message.get_first("missing").unwrap(), None);
+        assert!(msg.message.get_all("missing").unwrap().is_empty());
+    }
+
     #[test]
     fn test_len() {
         let msg = MessageFixture::new();
@@ -201,15 +764,15 @@ mod properties {
         msg.message.add_property(&"foo", &"b").unwrap();
         msg.message.add_property(&"bar", &"a").unwrap();
 
-        let num_props = msg.message.properties(&"", false).count();
+        let num_props = msg.message.properties(&"", false).unwrap().count();
         assert_eq!(num_props, 3);
 
-        let mut prop_keys: Vec<String> = msg.message.properties(&"", false).map(|x| x.0).collect();
+        let mut prop_keys: Vec<String> = msg.message.properties(&"", false).unwrap().map(|x| x.0).collect();
         prop_keys.sort();
         prop_keys.dedup();
         assert_eq!(prop_keys.len(), 2);
 
-        let mut prop_vals: Vec<String> = msg.message.properties(&"", false).map(|x| x.1).collect();
+        let mut prop_vals: Vec<String> = msg.message.properties(&"", false).unwrap().map(|x| x.1).collect();
         prop_vals.sort();
         prop_vals.dedup();
         assert_eq!(prop_vals.len(), 2);
@@ -244,16 +807,43 @@ mod properties {
         assert!(msg.message.property(&"foo").is_err());
     }
 
+    #[test]
+    fn test_del_prefix() {
+        let msg = MessageFixture::new();
+        msg.message.add_property("foo", "a").unwrap();
+        msg.message.add_property("foobar", "b").unwrap();
+        msg.message.add_property("bar", "c").unwrap();
+
+        msg.message.remove_all_properties_with_prefix("foo").unwrap();
+        assert!(msg.message.property("foo").is_err());
+        assert!(msg.message.property("foobar").is_err());
+        assert_eq!(msg.message.property("bar").unwrap(), "c");
+    }
+
+    #[test]
+    fn test_nul() {
+        let msg = MessageFixture::new();
+
+        match msg.message.add_property("foo\0bar", "a") {
+            Err(notmuch::Error::NulError(_)) => (),
+            other => panic!("expected NulError, got {:?}", other),
+        }
+        assert!(msg.message.add_property("foo", "a\0b").is_err());
+        assert!(msg.message.remove_property("foo\0bar", "a").is_err());
+        assert!(msg.message.remove_all_properties(Some("foo\0bar")).is_err());
+        assert!(msg.message.remove_all_properties_with_prefix("foo\0bar").is_err());
+    }
+
     #[test]
     fn test_getall() {
         let msg = MessageFixture::new();
         msg.message.add_property(&"foo", &"a").unwrap();
 
-        let prop_keys: Vec<String> = msg.message.properties(&"foo", false).map(|x| x.0).collect();
+        let prop_keys: Vec<String> = msg.message.properties(&"foo", false).unwrap().map(|x| x.0).collect();
         assert_eq!(prop_keys.len(), 1);
         assert_eq!(prop_keys, vec!["foo"]);
 
-        let prop_vals: Vec<String> = msg.message.properties(&"foo", false).map(|x| x.1).collect();
+        let prop_vals: Vec<String> = msg.message.properties(&"foo", false).unwrap().map(|x| x.1).collect();
         assert_eq!(prop_vals.len(), 1);
         assert_eq!(prop_vals, vec!["a"]);
     }
@@ -264,11 +854,11 @@ mod properties {
         msg.message.add_property(&"foo", &"a").unwrap();
         msg.message.add_property(&"foobar", &"b").unwrap();
 
-        let prop_keys: Vec<String> = msg.message.properties(&"foo", false).map(|x| x.0).collect();
+        let prop_keys: Vec<String> = msg.message.properties(&"foo", false).unwrap().map(|x| x.0).collect();
         assert_eq!(prop_keys.len(), 2);
         assert_eq!(prop_keys, vec!["foo", "foobar"]);
 
-        let prop_vals: Vec<String> = msg.message.properties(&"foo", false).map(|x| x.1).collect();
+        let prop_vals: Vec<String> = msg.message.properties(&"foo", false).unwrap().map(|x| x.1).collect();
         assert_eq!(prop_vals.len(), 2);
         assert_eq!(prop_vals, vec!["a", "b"]);
     }
@@ -279,13 +869,32 @@ mod properties {
         msg.message.add_property(&"foo", &"a").unwrap();
         msg.message.add_property(&"foobar", &"b").unwrap();
 
-        let prop_keys: Vec<String> = msg.message.properties(&"foo", true).map(|x| x.0).collect();
+        let prop_keys: Vec<String> = msg.message.properties(&"foo", true).unwrap().map(|x| x.0).collect();
         assert_eq!(prop_keys.len(), 1);
         assert_eq!(prop_keys, vec!["foo"]);
 
-        let prop_vals: Vec<String> = msg.message.properties(&"foo", true).map(|x| x.1).collect();
+        let prop_vals: Vec<String> = msg.message.properties(&"foo", true).unwrap().map(|x| x.1).collect();
         assert_eq!(prop_vals.len(), 1);
         assert_eq!(prop_vals, vec!["a"]);
     }
 }
 
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let (_mailbox, message) = index_raw(&dated_mail("Tue, 14 Oct 2025 12:30:00 +0200"));
+        message.add_tag("exported").unwrap();
+
+        let json = serde_json::to_value(&message).unwrap();
+
+        assert_eq!(json["id"], "dated@example.com");
+        assert_eq!(json["thread_id"], message.thread_id().as_ref());
+        assert_eq!(json["timestamp"], 1760437800);
+        assert_eq!(json["tags"], serde_json::json!(["exported"]));
+        assert_eq!(json["filenames"], serde_json::json!([message.filename().to_string_lossy()]));
+    }
+}
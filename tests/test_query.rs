@@ -95,3 +95,298 @@ fn test_iter_messages_ext() {
     
 }
 
+
+#[test]
+fn test_iter_messages_count() {
+    let q = QueryFixture::new();
+
+    let num = q.query.search_messages().unwrap().count();
+
+    assert_eq!(num as u32, q.query.count_messages().unwrap());
+}
+
+#[test]
+fn test_iter_threads_count() {
+    let q = QueryFixture::new();
+
+    let mut num = 0;
+    for _thread in q.query.search_threads().unwrap() {
+        num += 1;
+    }
+
+    assert_eq!(num, q.query.count_threads().unwrap());
+}
+
+#[test]
+fn test_collect_tags() {
+    let q = QueryFixture::new();
+
+    for (i, message) in q.query.search_messages().unwrap().enumerate() {
+        message.add_tag(if i % 2 == 0 { "even" } else { "odd" }).unwrap();
+        message.add_tag("all").unwrap();
+    }
+
+    let messages = q.query.search_messages().unwrap();
+    let mut tags: Vec<String> = messages.collect_tags().unwrap().collect();
+    tags.sort();
+
+    assert_eq!(tags, vec!["all", "even", "inbox", "odd", "unread"]);
+}
+
+#[test]
+fn test_sort() {
+    let mailbox = MailBox::new();
+    let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    for (id, date) in &[("old", "Mon, 01 Jan 2018 00:00:00 +0000"), ("new", "Tue, 01 Jan 2019 00:00:00 +0000")] {
+        let raw = format!("From: src@example.com\nTo: dst@example.com\nSubject: Sorted\nMessage-ID: <{}@example.com>\nDate: {}\n\nbody\n", id, date);
+        let filename = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        database.index_file(&filename, None).unwrap();
+    }
+
+    let query = database.create_query("*").unwrap();
+
+    query.set_sort(notmuch::Sort::OldestFirst);
+    assert_eq!(query.sort(), notmuch::Sort::OldestFirst);
+    assert_eq!(query.search_messages().unwrap().next().unwrap().id(), "old@example.com");
+
+    query.set_sort(notmuch::Sort::NewestFirst);
+    assert_eq!(query.sort(), notmuch::Sort::NewestFirst);
+    assert_eq!(query.search_messages().unwrap().next().unwrap().id(), "new@example.com");
+}
+
+#[test]
+fn test_exclude_flag() {
+    let mailbox = MailBox::new();
+    let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    for _ in 0..2 {
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        database.index_file(&filename, None).unwrap();
+    }
+    let deleted = database.create_query("*").unwrap().search_messages().unwrap().next().unwrap().id().to_string();
+    database.find_message(&deleted).unwrap().unwrap().add_tag("deleted").unwrap();
+
+    let query = database.create_query("*").unwrap();
+    query.add_tag_exclude("deleted").unwrap();
+    query.set_omit_excluded(notmuch::Exclude::Flag);
+
+    let excluded: Vec<(String, bool)> = query.search_messages().unwrap()
+        .map(|m| (m.id().to_string(), m.get_flag(notmuch::MessageFlag::Excluded).unwrap()))
+        .collect();
+    assert_eq!(excluded.len(), 2);
+    assert!(excluded.iter().all(|(id, flag)| *flag == (*id == deleted)));
+
+    query.set_omit_excluded(notmuch::Exclude::True);
+    assert_eq!(query.search_messages().unwrap().count(), 1);
+}
+
+#[test]
+fn test_count_no_results() {
+    let q = QueryFixture::new();
+    let database = Arc::new(notmuch::Database::open(&q.mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap());
+    let query = notmuch::Query::create(database, "not_a_matching_query").unwrap();
+
+    assert_eq!(query.count_messages().unwrap(), 0);
+    assert_eq!(query.count_threads().unwrap(), 0);
+    assert_eq!(query.search_messages().unwrap().count(), 0);
+    assert_eq!(query.search_threads().unwrap().count(), 0);
+}
+
+#[test]
+fn test_count_matches_search() {
+    let q = QueryFixture::new();
+
+    let count = q.query.count_messages().unwrap();
+    assert!(count > 0);
+    assert_eq!(q.query.search_messages().unwrap().count(), count as usize);
+}
+
+#[test]
+fn test_for_loops() -> Result<(), notmuch::Error> {
+    let q = QueryFixture::new();
+
+    let mut messages = 0;
+    for message in q.query.search_messages()? {
+        assert!(!message.id().is_empty());
+        messages += 1;
+    }
+    assert_eq!(messages, q.query.count_messages()?);
+
+    let mut threads = 0;
+    for thread in q.query.search_threads()? {
+        assert!(!thread.id().is_empty());
+        threads += 1;
+    }
+    assert_eq!(threads, q.query.count_threads()?);
+
+    Ok(())
+}
+
+#[test]
+fn test_drop_mid_iteration() -> Result<(), notmuch::Error> {
+    let q = QueryFixture::new();
+
+    // Messages outlive the set they were taken from.
+    let message = {
+        let mut messages = q.query.search_messages()?;
+        messages.next().unwrap()
+    };
+    assert!(!message.id().is_empty());
+
+    let mut tags = message.tags();
+    tags.next();
+    drop(tags);
+
+    let mut filenames = message.filenames();
+    assert!(filenames.next().is_some());
+    drop(filenames);
+
+    let mut threads = q.query.search_threads()?;
+    let thread = threads.next().unwrap();
+    drop(threads);
+    assert_eq!(thread.messages().count() as i32, thread.total_messages());
+
+    // The query and its results are still usable afterwards.
+    assert_eq!(q.query.search_messages()?.count(), q.query.count_messages()? as usize);
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_config_excludes() {
+    let mailbox = MailBox::new();
+    let (deleted, _) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+    mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+    let cmd = NotmuchCommand::new(&mailbox.path());
+    cmd.run(vec!["new"]).unwrap();
+
+    let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadWrite).unwrap();
+    database.set_config("search.exclude_tags", "deleted").unwrap();
+    database.find_message(&deleted).unwrap().unwrap().add_tag("deleted").unwrap();
+
+    let query = database.create_query("*").unwrap();
+    query.apply_config_excludes().unwrap();
+    let ids: Vec<String> = query.search_messages().unwrap().map(|m| m.id().into_owned()).collect();
+    assert_eq!(ids.len(), 1);
+    assert!(!ids.contains(&deleted));
+
+    // Searching for an excluded tag explicitly still finds it.
+    let query = database.create_query("tag:deleted").unwrap();
+    query.apply_config_excludes().unwrap();
+    assert_eq!(query.count_messages().unwrap(), 1);
+}
+
+#[cfg(feature = "v0_35")]
+#[test]
+fn test_create_query_with_syntax() {
+    let q = QueryFixture::new();
+    let database = q.query.database();
+
+    let xapian = database.create_query_with_syntax("foo and tag:inbox", notmuch::QuerySyntax::Xapian).unwrap();
+    assert_eq!(xapian.count_messages().unwrap(), q.query.count_messages().unwrap());
+
+    if notmuch::capabilities().sexp_queries {
+        let sexp = database.create_query_with_syntax("(and foo (tag inbox))", notmuch::QuerySyntax::Sexp).unwrap();
+        assert_eq!(sexp.count_messages().unwrap(), xapian.count_messages().unwrap());
+
+        match database.create_query_with_syntax("(and", notmuch::QuerySyntax::Sexp) {
+            Err(ref e) if e.status() == Some(notmuch::Status::BadQuerySyntax) => (),
+            other => panic!("expected BadQuerySyntax, got {:?}", other.map(|_| ())),
+        }
+    }
+}
+
+#[test]
+fn test_enumerate_messages() {
+    let q = QueryFixture::new();
+
+    let indices: Vec<usize> = q.query.search_messages().unwrap().enumerate().map(|(i, _)| i).collect();
+    let count = q.query.count_messages().unwrap() as usize;
+    assert_eq!(indices, (0..count).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_query_string() {
+    let q = QueryFixture::new();
+
+    assert_eq!(q.query.query_string(), "foo");
+}
+
+#[test]
+fn test_database() {
+    let q = QueryFixture::new();
+
+    assert_eq!(q.query.database().path(), q.mailbox.path());
+}
+
+#[test]
+fn test_builder() {
+    let mailbox = MailBox::new();
+    let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    for (id, date) in &[("old", "Mon, 01 Jan 2018 00:00:00 +0000"), ("mid", "Tue, 01 Jan 2019 00:00:00 +0000"), ("new", "Wed, 01 Jan 2020 00:00:00 +0000")] {
+        let raw = format!("From: src@example.com\nTo: dst@example.com\nSubject: Built\nMessage-ID: <{}@example.com>\nDate: {}\n\nbody\n", id, date);
+        let filename = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        database.index_file(&filename, None).unwrap();
+    }
+    database.find_message("mid@example.com").unwrap().unwrap().add_tag("spam").unwrap();
+    database.find_message("old@example.com").unwrap().unwrap().add_tag("deleted").unwrap();
+
+    let query = database.query_builder("*")
+        .sort(notmuch::Sort::NewestFirst)
+        .omit_excluded(notmuch::Exclude::True)
+        .exclude_tag("spam")
+        .exclude_tag("deleted")
+        .build()
+        .unwrap();
+
+    assert_eq!(query.query_string(), "*");
+    assert_eq!(query.sort(), notmuch::Sort::NewestFirst);
+    let ids: Vec<String> = query.search_messages().unwrap().map(|m| m.id().to_string()).collect();
+    assert_eq!(ids, vec!["new@example.com".to_string()]);
+}
+
+#[test]
+fn test_refine() {
+    let q = QueryFixture::new();
+    let database = q.query.database();
+    database.create_query("*").unwrap().search_messages().unwrap().next().unwrap().remove_tag("inbox").unwrap();
+
+    let all = database.create_query("*").unwrap();
+    let inbox = all.refine("tag:inbox").unwrap();
+
+    assert_eq!(inbox.query_string(), "(tag:inbox)");
+    assert_eq!(all.count_messages().unwrap(), 5);
+    assert_eq!(inbox.count_messages().unwrap(), 4);
+
+    let everything = database.create_query("").unwrap();
+    assert_eq!(everything.refine("tag:inbox").unwrap().query_string(), "(tag:inbox)");
+
+    let spaced = database.create_query(" * ").unwrap();
+    assert_eq!(spaced.refine("tag:inbox").unwrap().query_string(), "(tag:inbox)");
+
+    let refined = inbox.refine("subject:foo").unwrap();
+    assert_eq!(refined.query_string(), "((tag:inbox)) AND (subject:foo)");
+}
+
+#[test]
+fn test_search_messages_lazy() {
+    let mailbox = MailBox::new();
+    let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    for i in 0..200 {
+        let raw = format!("From: src@example.com\nTo: dst@example.com\nSubject: Bulk {}\nMessage-ID: <bulk{}@example.com>\n\nbody\n", i, i);
+        let filename = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        database.index_file(&filename, None).unwrap();
+    }
+
+    let query = database.create_query("*").unwrap();
+    let mut ids = std::collections::HashSet::new();
+    for message in query.search_messages_lazy().unwrap() {
+        assert!(message.header("subject").unwrap().unwrap().starts_with("Bulk "));
+        ids.insert(message.id().to_string());
+    }
+
+    assert_eq!(ids.len(), 200);
+}
@@ -78,6 +78,42 @@ mod mutable {
         assert!(tagset.message.tags().any(|x| x == "foo"));
     }
 
+    #[test]
+    fn test_iter_exact(){
+        let tagset = TagSetFixture::new(true, false);
+        tagset.message.remove_all_tags().unwrap();
+        tagset.message.add_tag("foo").unwrap();
+        tagset.message.add_tag("bar").unwrap();
+
+        let mut tags = Vec::new();
+        for tag in tagset.message.tags() {
+            tags.push(tag);
+        }
+        tags.sort();
+
+        assert_eq!(tags, vec!["bar", "foo"]);
+    }
+
+    #[test]
+    fn test_into_sorted_vec(){
+        let tagset = TagSetFixture::new(true, false);
+        tagset.message.remove_all_tags().unwrap();
+        for tag in &["zeta", "alpha", "mu", "beta"] {
+            tagset.message.add_tag(tag).unwrap();
+        }
+
+        assert_eq!(tagset.message.tags().into_sorted_vec(), vec!["alpha", "beta", "mu", "zeta"]);
+    }
+
+    #[test]
+    fn test_contains(){
+        let tagset = TagSetFixture::new(true, false);
+        tagset.message.add_tag("inbox").unwrap();
+
+        assert!(tagset.message.tags().contains("inbox"));
+        assert!(!tagset.message.tags().contains("spam"));
+    }
+
     #[test]
     fn test_discard(){
         let tagset = TagSetFixture::new(true, false);
@@ -139,4 +175,37 @@ mod mutable {
         assert!(!flags.contains('F'));
     }
 
+    #[test]
+    fn test_count(){
+        let tagset = TagSetFixture::new(true, false);
+        tagset.message.remove_all_tags().unwrap();
+        for tag in &["a", "b", "c", "b"] {
+            tagset.message.add_tag(tag).unwrap();
+        }
+
+        assert_eq!(tagset.message.tags().count(), 3);
+    }
+
+    #[test]
+    fn test_toggle_tag(){
+        let tagset = TagSetFixture::new(true, false);
+        let before = tagset.message.tags_vec();
+
+        assert!(tagset.message.toggle_tag("flagged").unwrap());
+        assert!(tagset.message.tags().contains("flagged"));
+        assert!(!tagset.message.toggle_tag("flagged").unwrap());
+        assert_eq!(tagset.message.tags_vec(), before);
+    }
+
+    #[test]
+    fn test_tags_vec(){
+        let tagset = TagSetFixture::new(true, false);
+        tagset.message.remove_all_tags().unwrap();
+        tagset.message.add_tag("work").unwrap();
+        tagset.message.add_tag("todo").unwrap();
+
+        assert_eq!(tagset.message.tags_vec(), vec!["todo", "work"]);
+        assert_eq!(tagset.message.tags_vec(), tagset.message.tags_vec());
+    }
+
 }
\ No newline at end of file
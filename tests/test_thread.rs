@@ -41,6 +41,26 @@ fn test_threadid() {
 }
 
 
+#[test]
+// Threads hash by their immutable ID; the atomics inside the database
+// handle they hold are not part of the hash.
+#[allow(clippy::mutable_key_type)]
+fn test_hash_by_id() {
+    use std::collections::HashSet;
+
+    let thread = ThreadFixture::new();
+    let database = notmuch::Database::open(&thread.mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+    let foo = database.create_query("foo").unwrap();
+    let bar = database.create_query("bar").unwrap();
+
+    let mut threads = HashSet::new();
+    threads.extend(foo.search_threads().unwrap());
+    threads.extend(bar.search_threads().unwrap());
+
+    assert_eq!(threads.len(), 1);
+    assert_eq!(threads.iter().next().unwrap().id(), thread.thread.id());
+}
+
 #[test]
 fn test_toplevel() {
     let thread = ThreadFixture::new();
@@ -99,4 +119,207 @@ fn test_tags() {
     let tags: Vec<String> = thread.thread.tags().collect();
     assert!(tags.iter().any(|x| x == "inbox"));
 }
- 
\ No newline at end of file
+ 
+
+#[test]
+fn test_match_flag() {
+    let thread = ThreadFixture::new();
+
+    let matched = thread.thread.messages().filter(|m| m.get_flag(notmuch::MessageFlag::Match).unwrap()).count();
+    assert_eq!(matched, 1);
+}
+
+#[test]
+fn test_set_flag() {
+    let thread = ThreadFixture::new();
+    let msg = thread.thread.toplevel_messages().next().unwrap();
+
+    msg.set_flag(notmuch::MessageFlag::Excluded, true);
+    assert!(msg.get_flag(notmuch::MessageFlag::Excluded).unwrap());
+
+    msg.set_flag(notmuch::MessageFlag::Excluded, false);
+    assert!(!msg.get_flag(notmuch::MessageFlag::Excluded).unwrap());
+}
+
+#[test]
+fn test_summary() {
+    let thread = ThreadFixture::new();
+
+    assert_eq!(thread.thread.total_messages(), 2);
+    assert_eq!(thread.thread.matched_messages(), 1);
+
+    let first = thread.thread.messages().next().unwrap();
+    assert_eq!(thread.thread.subject(), first.header("subject").unwrap().unwrap());
+
+    let dates: Vec<i64> = thread.thread.messages().map(|m| m.date()).collect();
+    assert_eq!(thread.thread.oldest_date(), *dates.iter().min().unwrap());
+    assert_eq!(thread.thread.newest_date(), *dates.iter().max().unwrap());
+}
+
+#[cfg(feature = "v0_26")]
+#[test]
+fn test_total_files() {
+    let thread = ThreadFixture::new();
+
+    assert_eq!(thread.thread.total_files(), 2);
+}
+
+#[test]
+fn test_authors_split() {
+    let mailbox = MailBox::new();
+
+    let (msgid, _) = mailbox.deliver(None, None, None, Some("alice@example.com".to_string()), vec![], true, None, false, false, false).unwrap();
+    let reply = vec![("In-Reply-To".to_string(), format!("<{}>", msgid))];
+    mailbox.deliver(None, Some("needle".to_string()), None, Some("bob@example.com".to_string()), reply.clone(), true, None, false, false, false).unwrap();
+    mailbox.deliver(None, None, None, Some("carol@example.com".to_string()), reply, true, None, false, false, false).unwrap();
+
+    let cmd = NotmuchCommand::new(&mailbox.path());
+    cmd.run(vec!["new"]).unwrap();
+
+    let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+    let query = database.create_query("needle").unwrap();
+    let thread = query.search_threads().unwrap().next().unwrap();
+
+    let (matched, mut unmatched) = thread.authors_split();
+    unmatched.sort();
+    assert_eq!(matched, vec!["bob@example.com".to_string()]);
+    assert_eq!(unmatched, vec!["alice@example.com".to_string(), "carol@example.com".to_string()]);
+}
+
+#[test]
+fn test_authors_split_all_matched() {
+    let thread = ThreadFixture::new();
+    let all = <notmuch::Database as notmuch::DatabaseExt>::create_query(
+        notmuch::Database::open(&thread.mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap(),
+        "*",
+    ).unwrap();
+    let thread = <notmuch::Query as notmuch::QueryExt>::search_threads(all).unwrap().next().unwrap();
+
+    assert_eq!(thread.authors_split(), (vec!["src@example.com".to_string()], vec![]));
+}
+
+#[test]
+fn test_reply_chain() {
+    let mailbox = MailBox::new();
+
+    let (root, _) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+    let (reply, _) = mailbox.deliver(None, None, None, None, vec![("In-Reply-To".to_string(), format!("<{}>", root))], true, None, false, false, false).unwrap();
+    let (nested, _) = mailbox.deliver(None, None, None, None, vec![("In-Reply-To".to_string(), format!("<{}>", reply))], true, None, false, false, false).unwrap();
+
+    let cmd = NotmuchCommand::new(&mailbox.path());
+    cmd.run(vec!["new"]).unwrap();
+
+    let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+    let query = database.create_query("*").unwrap();
+    let thread = query.search_threads().unwrap().next().unwrap();
+
+    assert_eq!(thread.messages().count(), 3);
+
+    let toplevel: Vec<_> = thread.toplevel_messages().collect();
+    assert_eq!(toplevel.len(), 1);
+    assert_eq!(toplevel[0].id(), root);
+
+    let replies: Vec<_> = toplevel[0].replies().collect();
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].id(), reply);
+    assert_eq!(replies[0].replies().map(|m| m.id().to_string()).collect::<Vec<_>>(), vec![nested]);
+}
+
+#[test]
+fn test_messages_with_depth() {
+    let mailbox = MailBox::new();
+    let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    let messages = [
+        ("root", None, "Mon, 01 Jan 2018 08:00:00 +0000"),
+        ("first", Some("root"), "Mon, 01 Jan 2018 09:00:00 +0000"),
+        ("nested", Some("first"), "Mon, 01 Jan 2018 10:00:00 +0000"),
+        ("second", Some("root"), "Mon, 01 Jan 2018 11:00:00 +0000"),
+    ];
+    for (id, parent, date) in &messages {
+        let in_reply_to = parent.map(|p| format!("In-Reply-To: <{}@example.com>\n", p)).unwrap_or_default();
+        let raw = format!("From: src@example.com\nTo: dst@example.com\nSubject: Nested thread\nMessage-ID: <{}@example.com>\n{}Date: {}\n\nbody\n", id, in_reply_to, date);
+        let filename = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        database.index_file(&filename, None).unwrap();
+    }
+
+    let query = database.create_query("*").unwrap();
+    let thread = query.search_threads().unwrap().next().unwrap();
+
+    let walked: Vec<(usize, String)> = thread.messages_with_depth()
+        .into_iter()
+        .map(|(depth, m)| (depth, m.id().into_owned()))
+        .collect();
+    assert_eq!(walked, vec![
+        (0, "root@example.com".to_string()),
+        (1, "first@example.com".to_string()),
+        (2, "nested@example.com".to_string()),
+        (1, "second@example.com".to_string()),
+    ]);
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_datetimes() {
+    use chrono::{TimeZone, Utc};
+
+    let mailbox = MailBox::new();
+    let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    let dates = [("root", None, "Mon, 01 Jan 2018 08:00:00 +0000"), ("reply", Some("root"), "Tue, 02 Jan 2018 09:30:00 +0100")];
+    for (id, parent, date) in &dates {
+        let in_reply_to = parent.map(|p| format!("In-Reply-To: <{}@example.com>\n", p)).unwrap_or_default();
+        let raw = format!("From: src@example.com\nTo: dst@example.com\nSubject: Dated thread\nMessage-ID: <{}@example.com>\n{}Date: {}\n\nbody\n", id, in_reply_to, date);
+        let filename = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        database.index_file(&filename, None).unwrap();
+    }
+
+    let query = database.create_query("*").unwrap();
+    let thread = query.search_threads().unwrap().next().unwrap();
+
+    assert_eq!(thread.oldest_datetime(), Utc.with_ymd_and_hms(2018, 1, 1, 8, 0, 0).unwrap());
+    assert_eq!(thread.newest_datetime(), Utc.with_ymd_and_hms(2018, 1, 2, 8, 30, 0).unwrap());
+}
+
+#[test]
+fn test_newest_oldest_message() {
+    let mailbox = MailBox::new();
+    let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    let dates = [
+        ("middle", None, "Tue, 02 Jan 2018 08:00:00 +0000"),
+        ("first", Some("middle"), "Mon, 01 Jan 2018 08:00:00 +0000"),
+        ("last", Some("middle"), "Wed, 03 Jan 2018 08:00:00 +0000"),
+    ];
+    for (id, parent, date) in &dates {
+        let in_reply_to = parent.map(|p| format!("In-Reply-To: <{}@example.com>\n", p)).unwrap_or_default();
+        let raw = format!("From: src@example.com\nTo: dst@example.com\nSubject: Dated thread\nMessage-ID: <{}@example.com>\n{}Date: {}\n\nbody\n", id, in_reply_to, date);
+        let filename = mailbox.deliver_raw(raw.as_bytes()).unwrap();
+        database.index_file(&filename, None).unwrap();
+    }
+
+    let query = database.create_query("*").unwrap();
+    let thread = query.search_threads().unwrap().next().unwrap();
+
+    assert_eq!(thread.total_messages(), 3);
+    assert_eq!(thread.oldest_message().unwrap().id(), "first@example.com");
+    assert_eq!(thread.newest_message().unwrap().id(), "last@example.com");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serialize() {
+    let thread = ThreadFixture::new();
+
+    let json = serde_json::to_value(&thread.thread).unwrap();
+
+    assert_eq!(json["thread_id"], thread.thread.id());
+    assert_eq!(json["subject"], "Test mail");
+    assert_eq!(json["matched"], 1);
+    assert_eq!(json["total"], 2);
+    assert_eq!(json["matched_authors"], serde_json::json!(["src@example.com"]));
+    assert_eq!(json["unmatched_authors"], serde_json::json!([]));
+    assert_eq!(json["oldest_timestamp"], thread.thread.oldest_date());
+    assert_eq!(json["newest_timestamp"], thread.thread.newest_date());
+    assert!(json["tags"].as_array().unwrap().iter().any(|t| t == "inbox"));
+}